@@ -1,153 +1,559 @@
 use super::auth;
-use super::sauce_errors;
+use super::retry::{self, RetryPolicy};
+use super::sauce_errors::{Result, ShelperError};
 use super::users;
-use std::error::Error;
-
-fn region_tunnel_api(region: &users::Region, tunnel_id: &str, owner: &str) -> std::string::String {
-    match region {
-        users::Region::US => format!(
-            "https://api.us-west-1.saucelabs.com/rest/v1/{}/tunnels/{}",
-            owner, tunnel_id
-        ),
-        users::Region::EU => format!(
-            "https://api.eu-central-1.saucelabs.com/rest/v1/{}/tunnels/{}",
-            owner, tunnel_id
-        ),
-    }
-}
-
-fn region_job_api(region: &users::Region, job_id: &str) -> std::string::String {
-    match region {
-        users::Region::US => format!("https://saucelabs.com/rest/v1.1/jobs/{}", job_id),
-        users::Region::EU => format!(
-            "https://eu-central-1.saucelabs.com/rest/v1.1/jobs/{}",
-            job_id
-        ),
-    }
-}
-
-/// `tunnel_raw` requires the Owner of a tunnel + the tunnel id to
-/// return data about the tunnel. Creation time, config, and more are returned from the API call.
-pub fn tunnel_raw(
-    owner: &users::User,
-    tunnel_id: &str,
-    super_admin: Option<&users::User>,
-) -> Result<String, Box<dyn Error>> {
-    let api = region_tunnel_api(&owner.region, tunnel_id, &owner.creds.username);
-    let client = reqwest::blocking::Client::new();
-    let auth: &users::User = match super_admin {
-        Some(admin) => admin,
-        None => owner,
-    };
-    let resp = client
-        .get(&api)
-        .basic_auth(&auth.creds.username, Some(&auth.creds.access_key))
-        .send()?;
-    if !resp.status().is_success() {
-        return Err(format!("{} response during req to {}. Are you looking for a tunnel owned by {}? Or owned by someone else?", resp.status(), api, &owner.creds.username))?;
-    }
-    return Ok(resp.text()?);
-}
-
-/// Returns the JSON info for a Job. `job_info` makes a REST call
-/// with given credentials to fetch the details of a single job.
-pub fn job_info(
-    owner: &users::User,
-    job_id: &str,
-    super_admin: Option<&users::User>,
-) -> Result<String, Box<dyn Error>> {
-    let auth: &users::User = match super_admin {
-        Some(admin) => admin,
-        None => owner,
-    };
+use std::thread;
+use std::time::{Duration, Instant};
 
-    let api = region_job_api(&owner.region, job_id);
+/// User-Agent sent with every request unless overwritten on the builder
+const DEFAULT_USER_AGENT: &str = concat!("shelper/", env!("CARGO_PKG_VERSION"));
 
-    let client = reqwest::blocking::Client::new();
-    let resp = client
-        .get(&api)
-        .basic_auth(&auth.creds.username, Some(&auth.creds.access_key))
-        .send()?;
-    if !resp.status().is_success() {
-        return Err(format!(
-            "{} response during GET req to {}",
-            resp.status(),
-            api
-        ))?;
-    }
-    return Ok(resp.text()?);
-}
-
-/// Get latest jobs for a user, limit of 500 at a time.  Returns
-/// a JSON object with details of each job
-pub fn recent_user_jobs(
-    owner: &users::User,
-    super_admin: Option<&users::User>,
-    limit: u64,
-) -> Result<String, Box<dyn Error>> {
-    if limit > 500 {
-        Err(format!(
-            "{} is too many jobs.  Limit is 500 for /user/jobs API. See Sauce Labs API docs",
-            limit
-        ))?;
-    }
-    let auth: &users::User = match super_admin {
-        Some(admin) => admin,
-        None => owner,
+/// Environment variable holding a base url override, ex. `http://127.0.0.1:8080`.
+/// Used by every `SauceClient` unless `SauceClientBuilder::base_url` is called.
+pub const BASE_URL_ENV: &str = "SHELPER_BASE_URL";
+
+/// `SauceClient` owns one connection pool plus the user that authenticates
+/// every request.  Build it once and reuse it for all calls to the Sauce REST API.
+#[derive(Debug)]
+pub struct SauceClient {
+    http: reqwest::blocking::Client,
+    user: users::User,
+    region: users::Region,
+    auto_region: bool,
+    base_url: Option<String>,
+    retry: RetryPolicy,
+}
+
+/// Configures a `SauceClient`.  Created with `SauceClient::builder`.
+#[derive(Debug)]
+pub struct SauceClientBuilder {
+    user: users::User,
+    region: Option<users::Region>,
+    auto_region: bool,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    user_agent: String,
+    base_url: Option<String>,
+    retry: RetryPolicy,
+}
+
+impl SauceClientBuilder {
+    /// Datacenter to send requests to.  Defaults to the region of the user.
+    pub fn region(mut self, region: users::Region) -> SauceClientBuilder {
+        self.region = Some(region);
+        self
+    }
+
+    /// Look for jobs and tunnels in every datacenter.  The user's home region
+    /// is tried first, then the others until one does not answer with a 404.
+    pub fn auto_region(mut self, auto_region: bool) -> SauceClientBuilder {
+        self.auto_region = auto_region;
+        self
+    }
+
+    /// Total time allowed for a single request, including reading the body.
+    pub fn timeout(mut self, timeout: Duration) -> SauceClientBuilder {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Time allowed to establish the connection.
+    pub fn connect_timeout(mut self, timeout: Duration) -> SauceClientBuilder {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> SauceClientBuilder {
+        self.user_agent = user_agent.to_string();
+        self
+    }
+
+    /// Send every request to `base_url` (ex. an internal proxy or a mock server)
+    /// instead of the saucelabs.com hosts.
+    pub fn base_url(mut self, base_url: &str) -> SauceClientBuilder {
+        self.base_url = Some(base_url.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Send requests to the saucelabs.com hosts, ignoring `SHELPER_BASE_URL`
+    pub fn default_hosts(mut self) -> SauceClientBuilder {
+        self.base_url = None;
+        self
+    }
+
+    /// How rate limited and failing requests are retried.
+    /// Defaults to `RetryPolicy::default()`.
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> SauceClientBuilder {
+        self.retry = retry;
+        self
+    }
+
+    pub fn build(self) -> Result<SauceClient> {
+        if let Some(base) = &self.base_url {
+            validate_base_url(base)?;
+        }
+        let mut http = reqwest::blocking::Client::builder().user_agent(&self.user_agent);
+        if let Some(timeout) = self.timeout {
+            http = http.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            http = http.connect_timeout(timeout);
+        }
+        let region = self.region.unwrap_or(self.user.region);
+        Ok(SauceClient {
+            http: http.build()?,
+            user: self.user,
+            region,
+            auto_region: self.auto_region,
+            base_url: self.base_url,
+            retry: self.retry,
+        })
+    }
+}
+
+/// The base url must be an absolute http(s) url.  A path is allowed so requests
+/// can go through a proxy mounted under a prefix.
+fn validate_base_url(base_url: &str) -> Result<()> {
+    let parsed = match url::Url::parse(base_url) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return Err(ShelperError::InvalidInput(format!(
+                "Invalid base url {}: {}",
+                base_url, e
+            )))
+        }
     };
-    let job_info_api = format!(
-        "https://saucelabs.com/rest/v1/{}/jobs?limit={}&full=true",
-        owner.creds.username, limit
-    );
+    match parsed.scheme() {
+        "http" | "https" => Ok(()),
+        other => Err(ShelperError::InvalidInput(format!(
+            "Invalid base url {}: scheme must be http or https, not {}",
+            base_url, other
+        ))),
+    }
+}
+
+impl SauceClient {
+    /// Client with the default settings for `user`.
+    pub fn new(user: users::User) -> Result<SauceClient> {
+        SauceClient::builder(user).build()
+    }
+
+    pub fn builder(user: users::User) -> SauceClientBuilder {
+        SauceClientBuilder {
+            user,
+            region: None,
+            auto_region: false,
+            timeout: None,
+            connect_timeout: None,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            base_url: std::env::var(BASE_URL_ENV)
+                .ok()
+                .filter(|base| !base.is_empty())
+                .map(|base| base.trim_end_matches('/').to_string()),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// The user whose credentials authenticate each request.
+    pub fn user(&self) -> &users::User {
+        &self.user
+    }
+
+    /// The datacenter requests are sent to.
+    pub fn region(&self) -> users::Region {
+        self.region
+    }
+
+    /// The base url override, if requests are not going to saucelabs.com.
+    pub fn base_url(&self) -> Option<&str> {
+        self.base_url.as_deref()
+    }
+
+    /// Joins `path` onto the base url override or the default `host`.
+    fn url(&self, host: &str, path: &str) -> String {
+        match &self.base_url {
+            Some(base) => format!("{}{}", base, path),
+            None => format!("{}{}", host, path),
+        }
+    }
+
+    /// Whether job and tunnel lookups search every datacenter
+    pub fn is_auto_region(&self) -> bool {
+        self.auto_region
+    }
+
+    /// `path` on the REST API host of the client's region
+    fn api_url(&self, path: &str) -> String {
+        self.api_url_in(self.region, path)
+    }
+
+    /// `path` on the REST API host of `region`
+    fn api_url_in(&self, region: users::Region, path: &str) -> String {
+        self.url(&region.api_host(), path)
+    }
+
+    /// `path` on the web app host of the client's region
+    fn app_url(&self, path: &str) -> String {
+        self.app_url_in(self.region, path)
+    }
+
+    /// `path` on the web app host of `region`
+    fn app_url_in(&self, region: users::Region, path: &str) -> String {
+        self.url(&region.app_host(), path)
+    }
+
+    /// GET `url` and return the body of a successful response.
+    fn get_text(&self, url: &str) -> Result<String> {
+        self.send(reqwest::Method::GET, url)
+    }
+
+    /// Send a request, retrying 429s, 5xx and connection problems according
+    /// to the `RetryPolicy`.  Returns the body of the first successful response.
+    fn send(&self, method: reqwest::Method, url: &str) -> Result<String> {
+        let resp = self.execute(method, url, |request| request)?;
+        Ok(resp.text()?)
+    }
+
+    /// Authenticate and send a `method` request to `url`, retrying like `send`.
+    /// `customize` adds to the request, ex. a body, and is called again for
+    /// every attempt.  A connection that can't be made is retried once.  A
+    /// timeout is only retried for GETs, the server may have acted on anything
+    /// else.  Returns the first successful response before its body is read.
+    fn execute(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        customize: impl Fn(reqwest::blocking::RequestBuilder) -> reqwest::blocking::RequestBuilder,
+    ) -> Result<reqwest::blocking::Response> {
+        let started = Instant::now();
+        let mut attempts = 0;
+        let mut connect_failed = false;
+        loop {
+            attempts += 1;
+            let request = customize(self.http.request(method.clone(), url));
+            let (err, server_delay) = match request
+                .basic_auth(&self.user.creds.username, Some(&self.user.creds.access_key))
+                .send()
+            {
+                Ok(resp) if resp.status().is_success() => return Ok(resp),
+                Ok(resp) if retry::retryable_status(resp.status().as_u16()) => {
+                    let delay = retry::server_delay(resp.headers());
+                    (self.status_error(resp, url), delay)
+                }
+                Ok(resp) => return Err(self.status_error(resp, url)),
+                Err(e) if e.is_connect() && !connect_failed => {
+                    connect_failed = true;
+                    (ShelperError::Network(e), None)
+                }
+                Err(e) if e.is_timeout() && !e.is_connect() && method.is_safe() => {
+                    (ShelperError::Network(e), None)
+                }
+                Err(e) => return Err(self.exhausted(attempts, started, ShelperError::Network(e))),
+            };
+            let retries = attempts - 1;
+            if retries >= self.retry.max_retries {
+                return Err(self.exhausted(attempts, started, err));
+            }
+            let delay = server_delay.unwrap_or_else(|| self.retry.backoff(retries));
+            if started.elapsed() + delay > self.retry.max_elapsed {
+                return Err(self.exhausted(attempts, started, err));
+            }
+            thread::sleep(delay);
+        }
+    }
+
+    /// The error returned once retrying is over.  Without a retry policy the
+    /// original error is returned as is.
+    fn exhausted(&self, attempts: u32, started: Instant, last: ShelperError) -> ShelperError {
+        if attempts == 1 {
+            return last;
+        }
+        ShelperError::RetriesExhausted {
+            attempts,
+            elapsed: started.elapsed(),
+            last: Box::new(last),
+        }
+    }
+
+    /// Turn an unsuccessful response into the matching `ShelperError`.
+    fn status_error(&self, resp: reqwest::blocking::Response, url: &str) -> ShelperError {
+        let url = url.to_string();
+        match resp.status().as_u16() {
+            401 | 403 => ShelperError::Unauthorized {
+                username: self.user.creds.username.clone(),
+                masked_key: auth::mask_key(self.user.creds.access_key.clone()),
+                url,
+            },
+            404 => ShelperError::NotFound { url, region: None },
+            429 => ShelperError::RateLimited {
+                url,
+                retry_after: resp
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse().ok()),
+            },
+            status => ShelperError::Api {
+                status,
+                url,
+                body: resp.text().unwrap_or_default(),
+            },
+        }
+    }
+
+    /// Regions to search for a job or tunnel.  Only the client's region,
+    /// unless auto region is on.  Then the user's home region comes first.
+    fn lookup_regions(&self) -> Vec<users::Region> {
+        if !self.auto_region {
+            return vec![self.region];
+        }
+        let home = self.user.region;
+        let mut regions = vec![home];
+        regions.extend(users::Region::all().iter().filter(|r| **r != home));
+        regions
+    }
+
+    /// GET `path` from each lookup region until one finds it.  Jobs and tunnels
+    /// only exist in one datacenter.  Without auto region only the client's
+    /// region is asked and a 404 is `NotFound` in that region.
+    fn region_scoped_get(&self, path: &str) -> Result<(String, users::Region)> {
+        self.region_scoped(|region| self.api_url_in(region, path))
+    }
+
+    /// Same as `region_scoped_get` for the url `url_in` gives for each region,
+    /// ex. a path on the web app host
+    fn region_scoped(
+        &self,
+        url_in: impl Fn(users::Region) -> String,
+    ) -> Result<(String, users::Region)> {
+        let mut last_url = String::new();
+        for region in self.lookup_regions() {
+            last_url = url_in(region);
+            match self.get_text(&last_url) {
+                Ok(body) => return Ok((body, region)),
+                Err(ShelperError::NotFound { .. }) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(ShelperError::NotFound {
+            url: last_url,
+            region: match self.auto_region {
+                true => None,
+                false => Some(self.region),
+            },
+        })
+    }
+
+    /// `tunnel_raw` requires the Owner of a tunnel + the tunnel id to
+    /// return data about the tunnel. Creation time, config, and more are returned from the API call.
+    pub fn tunnel_raw(&self, owner: &str, tunnel_id: &str) -> Result<String> {
+        self.find_tunnel(owner, tunnel_id).map(|(body, _)| body)
+    }
+
+    /// Same as `tunnel_raw` but also returns the region the tunnel was found in.
+    pub fn find_tunnel(&self, owner: &str, tunnel_id: &str) -> Result<(String, users::Region)> {
+        self.region_scoped_get(&format!("/rest/v1/{}/tunnels/{}", owner, tunnel_id))
+    }
+
+    /// Returns the JSON info for a Job. `job_info` makes a REST call
+    /// to fetch the details of a single job.
+    pub fn job_info(&self, job_id: &str) -> Result<String> {
+        self.find_job(job_id).map(|(body, _)| body)
+    }
+
+    /// Same as `job_info` but also returns the region the job was found in.
+    pub fn find_job(&self, job_id: &str) -> Result<(String, users::Region)> {
+        self.region_scoped_get(&format!("/rest/v1.1/jobs/{}", job_id))
+    }
+
+    /// The files stored with a job, ex. its logs and video.  Returns a JSON
+    /// object of asset type to file name, plus the region the job was found in.
+    pub fn find_job_assets(&self, owner: &str, job_id: &str) -> Result<(String, users::Region)> {
+        self.region_scoped_get(&format!("/rest/v1/{}/jobs/{}/assets", owner, job_id))
+    }
+
+    /// Start downloading the asset `file_name` of a job in `region`.  When
+    /// `offset` is above 0 only the bytes from `offset` on are asked for, to
+    /// resume a partial download.  A server that ignores the range answers 200
+    /// with the whole file instead of 206.  Read the body from the response.
+    pub fn job_asset(
+        &self,
+        owner: &str,
+        job_id: &str,
+        region: users::Region,
+        file_name: &str,
+        offset: u64,
+    ) -> Result<reqwest::blocking::Response> {
+        let url = self.api_url_in(
+            region,
+            &format!("/rest/v1/{}/jobs/{}/assets/{}", owner, job_id, file_name),
+        );
+        self.execute(reqwest::Method::GET, &url, |request| match offset {
+            0 => request,
+            _ => request.header(reqwest::header::RANGE, format!("bytes={}-", offset)),
+        })
+    }
+
+    /// Change the metadata of job `job_id` run by `owner` in `region`.
+    /// `changes` holds only the fields to set, ex. `{"passed": true}`.
+    /// Returns the updated job.
+    pub fn update_job(
+        &self,
+        owner: &str,
+        job_id: &str,
+        region: users::Region,
+        changes: &serde_json::Value,
+    ) -> Result<String> {
+        let url = self.api_url_in(region, &format!("/rest/v1/{}/jobs/{}", owner, job_id));
+        Ok(self
+            .execute(reqwest::Method::PUT, &url, |request| request.json(changes))?
+            .text()?)
+    }
+
+    /// Stop job `job_id` run by `owner` in `region` if it is still running.
+    /// Returns the job.
+    pub fn stop_job(&self, owner: &str, job_id: &str, region: users::Region) -> Result<String> {
+        let url = self.api_url_in(region, &format!("/rest/v1/{}/jobs/{}/stop", owner, job_id));
+        self.send(reqwest::Method::PUT, &url)
+    }
+
+    /// Get latest jobs for `owner`, limit of 500 at a time.  Returns
+    /// a JSON object with details of each job
+    pub fn recent_user_jobs(&self, owner: &str, limit: u64) -> Result<String> {
+        self.user_jobs_page(owner, limit, 0, None, None, &[])
+    }
+
+    /// One page of the jobs run by `owner`, newest first.  The first `skip` jobs
+    /// are skipped and `from`/`to` (unix timestamps) bound the creation time.
+    /// `filters` are more query parameters, ex. `("build", "nightly #12")`.
+    /// `limit` is at most 500.  Use `jobs::JobHistory` to walk every page.
+    pub fn user_jobs_page(
+        &self,
+        owner: &str,
+        limit: u64,
+        skip: u64,
+        from: Option<i64>,
+        to: Option<i64>,
+        filters: &[(String, String)],
+    ) -> Result<String> {
+        if limit > 500 {
+            return Err(ShelperError::InvalidInput(format!(
+                "{} is too many jobs.  Limit is 500 for /user/jobs API. See Sauce Labs API docs",
+                limit
+            )));
+        }
+        let mut path = format!("/rest/v1/{}/jobs?limit={}&full=true", owner, limit);
+        if skip > 0 {
+            path.push_str(&format!("&skip={}", skip));
+        }
+        if let Some(from) = from {
+            path.push_str(&format!("&from={}", from));
+        }
+        if let Some(to) = to {
+            path.push_str(&format!("&to={}", to));
+        }
+        if !filters.is_empty() {
+            let encoded = url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(filters)
+                .finish();
+            path.push_str(&format!("&{}", encoded));
+        }
+        self.get_text(&self.api_url(&path))
+    }
+
+    /// One page of the builds run by `owner`, newest first, skipping the first
+    /// `skip` builds.  `from`/`to` (unix timestamps) bound the creation time.
+    /// With `subaccounts` the builds of every user under `owner` are included,
+    /// which needs the key of an admin.  Use `builds::BuildQuery` to walk
+    /// every page.
+    pub fn user_builds_page(
+        &self,
+        owner: &str,
+        limit: u64,
+        skip: u64,
+        from: Option<i64>,
+        to: Option<i64>,
+        subaccounts: bool,
+    ) -> Result<String> {
+        let mut path = format!("/rest/v1/{}/builds?limit={}&full=true", owner, limit);
+        if skip > 0 {
+            path.push_str(&format!("&skip={}", skip));
+        }
+        if let Some(from) = from {
+            path.push_str(&format!("&from={}", from));
+        }
+        if let Some(to) = to {
+            path.push_str(&format!("&to={}", to));
+        }
+        if subaccounts {
+            path.push_str("&subaccounts=true");
+        }
+        self.get_text(&self.app_url(&path))
+    }
+
+    /// `all_jobs` makes an API call to the builds/:id/jobs endpoint
+    /// to fetch the job details for each job in the build.
+    pub fn all_jobs(&self, build_id: &str) -> Result<serde_json::Value> {
+        self.find_build_jobs(build_id).map(|(jobs, _)| jobs)
+    }
+
+    /// Same as `all_jobs` but also returns the region the build was found in.
+    pub fn find_build_jobs(&self, build_id: &str) -> Result<(serde_json::Value, users::Region)> {
+        let path = format!("/rest/v1/builds/{}/jobs", build_id);
+        let (body, region) = self.region_scoped(|region| self.app_url_in(region, &path))?;
+        Ok((jobs_array(&body, &self.app_url_in(region, &path))?, region))
+    }
 
-    let text_resp = reqwest::blocking::Client::new()
-        .get(&job_info_api)
-        .basic_auth(&auth.creds.username, Some(&auth.creds.access_key))
-        .send()?
-        .text()?;
-    return Ok(text_resp);
-}
-
-/// `all_jobs` makes an API call to the builds/:id/jobs endpoint
-/// to fetch the job details for each job in the build.
-pub fn all_jobs(build_id: String, user: users::User) -> Result<serde_json::Value, Box<dyn Error>> {
-    let build_api = format!("https://app.saucelabs.com/rest/v1/builds/{}/jobs", build_id);
-    let resp: serde_json::Value = reqwest::blocking::Client::new()
-        .get(&build_api)
-        .basic_auth(&user.creds.username, Some(&user.creds.access_key))
-        .send()?
-        .json()?;
+    /// The jobs of build `build_id` in `region`, ex. the region of a `Build`
+    pub fn build_jobs_in(
+        &self,
+        build_id: &str,
+        region: users::Region,
+    ) -> Result<serde_json::Value> {
+        let url = self.app_url_in(region, &format!("/rest/v1/builds/{}/jobs", build_id));
+        jobs_array(&self.get_text(&url)?, &url)
+    }
+
+    /// `build_info` uses the v1/builds/:id endpoint to fetch
+    /// all the build meta data as a json object
+    pub fn build_info(&self, build_id: &str) -> Result<serde_json::Value> {
+        self.find_build(build_id).map(|(build, _)| build)
+    }
+
+    /// Same as `build_info` but also returns the region the build was found in.
+    pub fn find_build(&self, build_id: &str) -> Result<(serde_json::Value, users::Region)> {
+        let path = format!("/rest/v1/builds/{}", build_id);
+        let (body, region) = self.region_scoped(|region| self.app_url_in(region, &path))?;
+        let build = serde_json::from_str(&body).map_err(|e| ShelperError::deserialize(e, &body))?;
+        Ok((build, region))
+    }
+}
+
+/// Parse the body of a builds/:id/jobs response from `url`, which must hold
+/// a `jobs` array
+fn jobs_array(body: &str, url: &str) -> Result<serde_json::Value> {
+    let resp: serde_json::Value =
+        serde_json::from_str(body).map_err(|e| ShelperError::deserialize(e, body))?;
     if resp["jobs"].is_array() {
-        return Ok(resp);
+        Ok(resp)
     } else {
-        let masked_key = auth::mask_key(user.creds.access_key);
-        return Err(Box::new(sauce_errors::build::NoJobs::new(
-            &user.creds.username,
-            &masked_key,
-            &build_api,
-            resp,
-        )));
-    };
+        let e = serde::de::Error::custom(format!("no jobs array in the response from {}", url));
+        Err(ShelperError::deserialize(e, body))
+    }
 }
 
-/// `build_info` uses the v1/builds/:id endpoint to fetch
-/// all the build meta data as a json object
-pub fn build_info(build_id: &str, user: users::User) -> Result<serde_json::Value, Box<dyn Error>> {
-    let build_api = format!("https://app.saucelabs.com/rest/v1/builds/{}", build_id);
-    let resp: serde_json::Value = reqwest::blocking::Client::new()
-        .get(&build_api)
-        .basic_auth(&user.creds.username, Some(&user.creds.access_key))
-        .send()?
-        .json()?;
-    return Ok(resp);
-}
+#[cfg(test)]
+use super::fixtures::FixtureServer;
 
 #[test]
 /// use the recent_user_jobs api call and confirm
 /// we only get the requested number of jobs as raw json
 fn json_user_last_3_jobs() {
-    let real_user_env_vars = super::users::User::new(None, None, None);
-    let jobs_json = super::api::recent_user_jobs(&real_user_env_vars, None, 3).unwrap();
+    let server = FixtureServer::start();
+    let client = server.client();
+    let jobs_json = client
+        .recent_user_jobs(&client.user().creds.username, 3)
+        .unwrap();
     let last_3_jobs: serde_json::Value = serde_json::from_str(&jobs_json).unwrap();
     println!(
         "{}\nLength of jobs_json: {}",
@@ -159,9 +565,9 @@ fn json_user_last_3_jobs() {
 
 #[test]
 fn over_500_limit() {
-    let real_user_env_vars = super::users::User::new(None, None, None);
-    // let _jobs_json = super::jobs::recent_user_jobs(&real_user_env_vars, None, 505).unwrap();
-    match super::api::recent_user_jobs(&real_user_env_vars, None, 505) {
+    let server = FixtureServer::start();
+    let client = server.client();
+    match client.recent_user_jobs(&client.user().creds.username, 505) {
         Ok(_) => println!("Shouldn't be here"),
         Err(e) => {
             println!("{:?}", e);
@@ -176,12 +582,24 @@ fn over_500_limit() {
 #[test]
 #[should_panic]
 fn all_jobs_bad_input() {
+    let server = FixtureServer::start();
+    server.route(
+        "GET",
+        "/rest/v1/builds/91ee45d589ce4177981bf22f911f22c5/jobs",
+        401,
+        r#"{"message": "Not authorized"}"#,
+    );
     let fake_user = super::users::User::new(
         Some("bad.user12b1581b".to_string()),
         Some("1285-fake-b128b519".to_string()),
         None,
-    );
-    match super::api::all_jobs("91ee45d589ce4177981bf22f911f22c5".to_string(), fake_user) {
+    )
+    .unwrap();
+    let client = SauceClient::builder(fake_user)
+        .base_url(&server.url())
+        .build()
+        .unwrap();
+    match client.all_jobs("91ee45d589ce4177981bf22f911f22c5") {
         Ok(resp) => assert_eq!(resp["jobs"].as_array().unwrap().len(), 32),
         Err(e) => assert_eq!(e.to_string(), ""),
     }
@@ -189,8 +607,9 @@ fn all_jobs_bad_input() {
 
 #[test]
 fn get_build_data() {
-    let real_user = super::users::User::new(None, None, None);
-    let resp = match super::api::build_info("91ee45d589ce4177981bf22f911f22c5", real_user) {
+    let server = FixtureServer::start();
+    let client = server.client();
+    let resp = match client.build_info("91ee45d589ce4177981bf22f911f22c5") {
         Ok(resp) => resp,
         Err(e) => panic!("{}", e),
     };
@@ -203,8 +622,9 @@ fn get_build_data() {
 
 #[test]
 fn create_new_build_object() {
-    let real_user = super::users::User::new(None, None, None);
-    let mybuild = match super::builds::Build::new("91ee45d589ce4177981bf22f911f22c5", real_user) {
+    let server = FixtureServer::start();
+    let client = server.client();
+    let mybuild = match super::builds::Build::new(&client, "91ee45d589ce4177981bf22f911f22c5") {
         Ok(b) => b,
         Err(e) => panic!("{}", e),
     };
@@ -217,12 +637,294 @@ fn create_new_build_object() {
 
 #[test]
 fn get_tunnel_raw() {
-    let real_user_env_vars = super::users::User::new(None, None, None);
-    let tunnel_deets = super::api::tunnel_raw(
-        &real_user_env_vars,
-        "20073ff17a234bec951b7a51a1bce2ad",
-        Some(&real_user_env_vars),
+    let server = FixtureServer::start();
+    let client = server.client();
+    let tunnel_deets = client
+        .tunnel_raw(
+            &client.user().creds.username,
+            "20073ff17a234bec951b7a51a1bce2ad",
+        )
+        .unwrap();
+    println!("{}", tunnel_deets)
+}
+
+#[test]
+fn base_url_overrides_every_host() {
+    let user = super::users::User::new(
+        Some("fake.user".to_string()),
+        Some("fake-key".to_string()),
+        Some(super::users::Region::EU),
     )
     .unwrap();
-    println!("{}", tunnel_deets)
+    let client = SauceClient::builder(user)
+        .base_url("http://127.0.0.1:8080/")
+        .build()
+        .unwrap();
+    assert_eq!(client.region(), super::users::Region::EU);
+    assert_eq!(
+        client.api_url("/rest/v1.1/jobs/abc"),
+        "http://127.0.0.1:8080/rest/v1.1/jobs/abc"
+    );
+}
+
+#[test]
+fn base_url_keeps_proxy_prefix() {
+    let user = super::users::User::new(
+        Some("fake.user".to_string()),
+        Some("fake-key".to_string()),
+        None,
+    )
+    .unwrap();
+    let client = SauceClient::builder(user)
+        .base_url("https://proxy.internal/sauce/")
+        .build()
+        .unwrap();
+    assert_eq!(client.base_url(), Some("https://proxy.internal/sauce"));
+    assert_eq!(
+        client.url("https://app.saucelabs.com", "/rest/v1/builds/abc"),
+        "https://proxy.internal/sauce/rest/v1/builds/abc"
+    );
+}
+
+#[test]
+fn invalid_base_url_rejected() {
+    assert!(validate_base_url("127.0.0.1:8080").is_err());
+    assert!(validate_base_url("ftp://127.0.0.1").is_err());
+    assert!(validate_base_url("http://127.0.0.1:8080").is_ok());
+}
+
+#[test]
+fn missing_fixture_is_not_found() {
+    let server = FixtureServer::start();
+    let client = server.client();
+    assert!(client.job_info("00000000000000000000000000000000").is_err());
+    let request = &server.requests()[0];
+    assert_eq!(
+        request.path,
+        "/rest/v1.1/jobs/00000000000000000000000000000000"
+    );
+    assert_eq!(request.header("user-agent"), Some(DEFAULT_USER_AGENT));
+    assert!(request.header("authorization").is_some());
+}
+
+#[test]
+fn status_codes_map_to_errors() {
+    let server = FixtureServer::start();
+    server.route("GET", "/rest/v1/builds/unauthorized", 401, "{}");
+    server.route_with_headers(
+        "GET",
+        "/rest/v1/builds/limited",
+        429,
+        &[("Retry-After", "7")],
+        "{}",
+    );
+    let client = server
+        .builder()
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap();
+    match client.build_info("unauthorized") {
+        Err(ShelperError::Unauthorized { masked_key, .. }) => assert_eq!(masked_key, "fixtu"),
+        other => panic!("expected Unauthorized, got {:?}", other),
+    }
+    match client.build_info("limited") {
+        Err(ShelperError::RateLimited { retry_after, .. }) => assert_eq!(retry_after, Some(7)),
+        other => panic!("expected RateLimited, got {:?}", other),
+    }
+    match client.build_info("missing") {
+        Err(ShelperError::NotFound { .. }) => (),
+        other => panic!("expected NotFound, got {:?}", other),
+    }
+    match client.job_info("missing") {
+        Err(ShelperError::NotFound { region, .. }) => {
+            assert_eq!(region, Some(super::users::Region::US))
+        }
+        other => panic!("expected NotFound, got {:?}", other),
+    }
+}
+
+#[test]
+fn not_found_without_auto_region_asks_one_region() {
+    let server = FixtureServer::start();
+    let path = "/rest/v1.1/jobs/30b9be879aa84313800c987b7aa325e8";
+    server.route("GET", path, 404, r#"{"error": "Not found"}"#);
+    server.route(
+        "GET",
+        path,
+        200,
+        r#"{"id": "30b9be879aa84313800c987b7aa325e8"}"#,
+    );
+    let client = server.client();
+    let err = client
+        .job_info("30b9be879aa84313800c987b7aa325e8")
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .ends_with("use --region auto to search every region"));
+    match err {
+        ShelperError::NotFound { region, .. } => {
+            assert_eq!(region, Some(super::users::Region::US))
+        }
+        other => panic!("expected NotFound, got {:?}", other),
+    }
+    // no other datacenter is asked
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn retries_rate_limited_requests() {
+    let server = FixtureServer::start();
+    let path = "/rest/v1/builds/91ee45d589ce4177981bf22f911f22c5";
+    server.route_with_headers("GET", path, 429, &[("Retry-After", "0")], "{}");
+    server.route("GET", path, 503, "{}");
+    server.route(
+        "GET",
+        path,
+        200,
+        r#"{"id": "91ee45d589ce4177981bf22f911f22c5"}"#,
+    );
+    let client = server
+        .builder()
+        .retry_policy(RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        })
+        .build()
+        .unwrap();
+    let resp = client
+        .build_info("91ee45d589ce4177981bf22f911f22c5")
+        .unwrap();
+    assert_eq!(resp["id"], "91ee45d589ce4177981bf22f911f22c5");
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+fn gives_up_when_retries_run_out() {
+    let server = FixtureServer::start();
+    server.route("GET", "/rest/v1/builds/down", 503, "maintenance");
+    let client = server
+        .builder()
+        .retry_policy(RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        })
+        .build()
+        .unwrap();
+    match client.build_info("down") {
+        Err(ShelperError::RetriesExhausted { attempts, last, .. }) => {
+            assert_eq!(attempts, 3);
+            match *last {
+                ShelperError::Api { status, .. } => assert_eq!(status, 503),
+                other => panic!("expected Api, got {:?}", other),
+            }
+        }
+        other => panic!("expected RetriesExhausted, got {:?}", other),
+    }
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+fn refused_connection_is_retried_once() {
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", closed.local_addr().unwrap());
+    drop(closed);
+    let client = FixtureServer::start()
+        .builder()
+        .base_url(&url)
+        .retry_policy(RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        })
+        .build()
+        .unwrap();
+    match client.build_info("91ee45d589ce4177981bf22f911f22c5") {
+        Err(ShelperError::RetriesExhausted { attempts, last, .. }) => {
+            assert_eq!(attempts, 2);
+            assert!(matches!(*last, ShelperError::Network(_)));
+        }
+        other => panic!("expected RetriesExhausted, got {:?}", other),
+    }
+}
+
+#[test]
+fn timed_out_put_is_not_retried() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let accepted = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let held = std::sync::Arc::clone(&accepted);
+    // accept and never answer
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            held.lock().unwrap().push(stream);
+        }
+    });
+    let client = FixtureServer::start()
+        .builder()
+        .base_url(&url)
+        .timeout(Duration::from_millis(100))
+        .retry_policy(RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        })
+        .build()
+        .unwrap();
+    match client.stop_job("max.dobeck", "abc", super::users::Region::US) {
+        Err(ShelperError::Network(e)) => assert!(e.is_timeout()),
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+    assert_eq!(accepted.lock().unwrap().len(), 1);
+
+    match client.build_info("abc") {
+        Err(ShelperError::RetriesExhausted { attempts, .. }) => assert_eq!(attempts, 6),
+        other => panic!("expected RetriesExhausted, got {:?}", other),
+    }
+    assert_eq!(accepted.lock().unwrap().len(), 7);
+}
+
+#[test]
+fn endpoints_follow_the_region() {
+    let user = super::users::User::new(
+        Some("fake.user".to_string()),
+        Some("fake-key".to_string()),
+        Some(super::users::Region::APAC),
+    )
+    .unwrap();
+    let client = SauceClient::builder(user).default_hosts().build().unwrap();
+    assert_eq!(client.base_url(), None);
+    assert_eq!(
+        client.api_url("/rest/v1.1/jobs/abc"),
+        "https://api.apac-southeast-1.saucelabs.com/rest/v1.1/jobs/abc"
+    );
+    assert_eq!(
+        client.app_url("/rest/v1/builds/abc"),
+        "https://app.apac-southeast-1.saucelabs.com/rest/v1/builds/abc"
+    );
+}
+
+#[test]
+fn auto_region_falls_back_on_404() {
+    let server = FixtureServer::start();
+    let path = "/rest/v1.1/jobs/30b9be879aa84313800c987b7aa325e8";
+    server.route("GET", path, 404, r#"{"error": "Not found"}"#);
+    server.route(
+        "GET",
+        path,
+        200,
+        r#"{"id": "30b9be879aa84313800c987b7aa325e8"}"#,
+    );
+    let client = server.builder().auto_region(true).build().unwrap();
+    let (_, region) = client.find_job("30b9be879aa84313800c987b7aa325e8").unwrap();
+    // home region (US) answered 404 so the next datacenter was tried
+    assert_eq!(region, super::users::Region::EU);
+    assert_eq!(server.requests().len(), 2);
+
+    match client.find_job("missing") {
+        Err(ShelperError::NotFound { .. }) => (),
+        other => panic!("expected NotFound, got {:?}", other),
+    }
+    assert_eq!(
+        server.requests().len(),
+        2 + super::users::Region::all().len()
+    );
 }
@@ -0,0 +1,278 @@
+use super::api;
+use super::jobs::JobDetails;
+use super::sauce_errors::{Result, ShelperError};
+use super::users;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// One file stored with a job, ex. its video or the Selenium server log
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Asset {
+    /// What the API calls the asset, ex. `sauce-log` or `screenshots`
+    pub kind: String,
+    /// The file to download, ex. `log.json`.  Screenshots come as one
+    /// `screenshots.zip`.
+    pub file_name: String,
+}
+
+/// How far along a download is.  Passed to the progress callback after each
+/// chunk is written.
+#[derive(Debug, Clone)]
+pub struct Progress<'a> {
+    pub asset: &'a Asset,
+    /// Bytes on disk so far, including the ones from an earlier attempt
+    pub downloaded: u64,
+    /// Size of the whole file, when the server says
+    pub total: Option<u64>,
+}
+
+/// Every asset of a job: its logs, video, screenshots, HAR files and so on
+#[derive(Serialize, Debug, Clone)]
+pub struct JobAssets {
+    pub job_id: String,
+    pub owner: String,
+    pub assets: Vec<Asset>,
+    #[serde(skip)]
+    pub region: users::Region,
+}
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+impl JobAssets {
+    /// List the assets of job `job_id` run by `owner`
+    pub fn new(client: &api::SauceClient, owner: &str, job_id: &str) -> Result<JobAssets> {
+        let (body, region) = client.find_job_assets(owner, job_id)?;
+        let listing: serde_json::Value =
+            serde_json::from_str(&body).map_err(|e| ShelperError::deserialize(e, &body))?;
+        Ok(JobAssets {
+            job_id: job_id.to_string(),
+            owner: owner.to_string(),
+            assets: parse_listing(&listing),
+            region,
+        })
+    }
+
+    /// The asset with this kind or file name, ex. `video` or `video.mp4`
+    pub fn get(&self, name: &str) -> Option<&Asset> {
+        self.assets
+            .iter()
+            .find(|a| a.kind == name || a.file_name == name)
+    }
+
+    /// Stream `asset` into `dir`, creating it if needed, and return the path
+    /// of the file.  Bytes go to `<file>.part` first, so an interrupted
+    /// download picks up where it stopped the next time.  A file that was
+    /// already downloaded is not fetched again.
+    pub fn download(
+        &self,
+        client: &api::SauceClient,
+        asset: &Asset,
+        dir: &Path,
+        progress: &mut dyn FnMut(&Progress),
+    ) -> Result<PathBuf> {
+        fs::create_dir_all(dir).map_err(|e| ShelperError::io(e, dir))?;
+        let path = dir.join(&asset.file_name);
+        if let Ok(done) = fs::metadata(&path) {
+            progress(&Progress {
+                asset,
+                downloaded: done.len(),
+                total: Some(done.len()),
+            });
+            return Ok(path);
+        }
+        let partial = dir.join(format!("{}.part", asset.file_name));
+        let offset = fs::metadata(&partial).map(|m| m.len()).unwrap_or(0);
+        let mut resp = match client.job_asset(
+            &self.owner,
+            &self.job_id,
+            self.region,
+            &asset.file_name,
+            offset,
+        ) {
+            Ok(resp) => resp,
+            // nothing left past `offset`, the last attempt got every byte
+            Err(ShelperError::Api { status: 416, .. }) if offset > 0 => {
+                fs::rename(&partial, &path).map_err(|e| ShelperError::io(e, &path))?;
+                return Ok(path);
+            }
+            Err(e) => return Err(e),
+        };
+        // a 200 instead of a 206 means the range was ignored, start over
+        let resumed = offset > 0 && resp.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+        let mut downloaded = if resumed { offset } else { 0 };
+        let total = resp.content_length().map(|len| len + downloaded);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resumed)
+            .truncate(!resumed)
+            .open(&partial)
+            .map_err(|e| ShelperError::io(e, &partial))?;
+        let mut chunk = vec![0; CHUNK_SIZE];
+        loop {
+            let read = resp
+                .read(&mut chunk)
+                .map_err(|e| ShelperError::io(e, &partial))?;
+            if read == 0 {
+                break;
+            }
+            file.write_all(&chunk[..read])
+                .map_err(|e| ShelperError::io(e, &partial))?;
+            downloaded += read as u64;
+            progress(&Progress {
+                asset,
+                downloaded,
+                total,
+            });
+        }
+        file.flush().map_err(|e| ShelperError::io(e, &partial))?;
+        fs::rename(&partial, &path).map_err(|e| ShelperError::io(e, &path))?;
+        Ok(path)
+    }
+
+    /// `download` every asset into `dir`.  Stops at the first one that fails,
+    /// running it again resumes from there.
+    pub fn download_all(
+        &self,
+        client: &api::SauceClient,
+        dir: &Path,
+        progress: &mut dyn FnMut(&Progress),
+    ) -> Result<Vec<PathBuf>> {
+        self.assets
+            .iter()
+            .map(|asset| self.download(client, asset, dir, progress))
+            .collect()
+    }
+}
+
+impl JobDetails {
+    /// List the assets of this job
+    pub fn assets(&self, client: &api::SauceClient) -> Result<JobAssets> {
+        let mut assets = JobAssets::new(client, &self.owner, &self.id)?;
+        assets.region = self.region;
+        Ok(assets)
+    }
+}
+
+/// The listing maps each kind of asset to a file name, `null` when the job
+/// doesn't have one.  Some kinds point at the same file, ex. `video` and
+/// `video.mp4`, and screenshots are a list that downloads as one zip.
+fn parse_listing(listing: &serde_json::Value) -> Vec<Asset> {
+    let mut assets: Vec<Asset> = vec![];
+    let map = match listing.as_object() {
+        Some(map) => map,
+        None => return assets,
+    };
+    for (kind, value) in map {
+        let file_name = match value {
+            serde_json::Value::String(name) => name.clone(),
+            serde_json::Value::Array(files) if !files.is_empty() => format!("{}.zip", kind),
+            _ => continue,
+        };
+        // the name becomes a path in the download directory
+        if file_name.is_empty()
+            || file_name.contains(['/', '\\'])
+            || file_name.starts_with('.')
+            || assets.iter().any(|a| a.file_name == file_name)
+        {
+            continue;
+        }
+        assets.push(Asset {
+            kind: kind.clone(),
+            file_name,
+        });
+    }
+    assets
+}
+
+#[cfg(test)]
+use super::fixtures::{FixtureServer, OWNER};
+
+#[test]
+fn assets_download_and_resume() {
+    let server = FixtureServer::start();
+    let client = server.client();
+    let job_id = "30b9be879aa84313800c987b7aa325e8";
+    let assets = JobAssets::new(&client, OWNER, job_id).unwrap();
+    let names: Vec<&str> = assets.assets.iter().map(|a| a.file_name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "appium-server.log",
+            "logcat.log",
+            "network.har",
+            "log.json",
+            "screenshots.zip",
+            "selenium-server.log",
+            "video.mp4"
+        ]
+    );
+    assert_eq!(assets.get("video").unwrap().file_name, "video.mp4");
+    assert_eq!(assets.get("log.json").unwrap().kind, "sauce-log");
+
+    let dir = std::env::temp_dir().join(format!("shelper-assets-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let path = |file: &str| format!("/rest/v1/{}/jobs/{}/assets/{}", OWNER, job_id, file);
+    let mut seen = vec![];
+    let mut progress = |p: &Progress| seen.push((p.asset.file_name.clone(), p.downloaded, p.total));
+
+    // a fresh download
+    server.route("GET", &path("log.json"), 200, r#"[{"status": 200}]"#);
+    let log = assets
+        .download(
+            &client,
+            assets.get("sauce-log").unwrap(),
+            &dir,
+            &mut progress,
+        )
+        .unwrap();
+    assert_eq!(fs::read_to_string(&log).unwrap(), r#"[{"status": 200}]"#);
+    assert!(server.requests()[1].header("range").is_none());
+
+    // an interrupted download asks for the rest of the file
+    fs::write(dir.join("video.mp4.part"), "vid").unwrap();
+    server.route_with_headers(
+        "GET",
+        &path("video.mp4"),
+        206,
+        &[("Content-Range", "bytes 3-10/11")],
+        "eo frames",
+    );
+    let video = assets
+        .download(&client, assets.get("video").unwrap(), &dir, &mut progress)
+        .unwrap();
+    assert_eq!(fs::read_to_string(&video).unwrap(), "video frames");
+    assert_eq!(server.requests()[2].header("range"), Some("bytes=3-"));
+    assert!(!dir.join("video.mp4.part").exists());
+
+    // a server that ignores the range sends the whole file again
+    fs::write(dir.join("selenium-server.log.part"), "stale").unwrap();
+    server.route("GET", &path("selenium-server.log"), 200, "whole log");
+    let selenium = assets
+        .download(
+            &client,
+            assets.get("selenium-log").unwrap(),
+            &dir,
+            &mut progress,
+        )
+        .unwrap();
+    assert_eq!(fs::read_to_string(&selenium).unwrap(), "whole log");
+
+    // finished files are skipped
+    assets
+        .download(&client, assets.get("video").unwrap(), &dir, &mut progress)
+        .unwrap();
+    assert_eq!(server.requests().len(), 4);
+    assert_eq!(
+        seen,
+        vec![
+            ("log.json".to_string(), 17, Some(17)),
+            ("video.mp4".to_string(), 12, Some(12)),
+            ("selenium-server.log".to_string(), 9, Some(9)),
+            ("video.mp4".to_string(), 12, Some(12)),
+        ]
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
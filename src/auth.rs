@@ -1,3 +1,4 @@
+use super::sauce_errors::{Result, ShelperError};
 use std::env;
 
 /// SAUCE_USERNAME:SAUCE_ACCESS_KEY are used for authentication.
@@ -10,62 +11,43 @@ pub struct Credentials {
 
 /// defaults to using SAUCE_USERNAME and SAUCE_ACCESS_KEY environment variables.
 /// Can be overwritten with custom credentials.
-pub fn set_credentials(username: Option<String>, access_key: Option<String>) -> Credentials {
+pub fn set_credentials(
+    username: Option<String>,
+    access_key: Option<String>,
+) -> Result<Credentials> {
     match (username, access_key) {
-        (None, None) => return env_credentials(),
-        (Some(username), Some(access_key)) => {
-            return Credentials {
-                username,
-                access_key,
-            }
-        }
-        (Some(user), None) => {
-            return Credentials {
-                username: user,
-                access_key: "".to_string(),
-            };
-        }
-        (None, Some(_)) => return env_credentials(),
+        (None, None) => env_credentials(),
+        (Some(username), Some(access_key)) => Ok(Credentials {
+            username,
+            access_key,
+        }),
+        (Some(user), None) => Ok(Credentials {
+            username: user,
+            access_key: "".to_string(),
+        }),
+        (None, Some(_)) => env_credentials(),
     }
 }
 
 /// gets the env variables  SAUCE_USERNAME and SAUCE_ACCESS_KEY.
-fn env_credentials() -> Credentials {
+fn env_credentials() -> Result<Credentials> {
     const USERNAME: &str = "SAUCE_USERNAME";
     const ACCESS_KEY: &str = "SAUCE_ACCESS_KEY";
-    let sauce_username = match env::var(USERNAME) {
-        Ok(name) => name,
-        Err(e) => panic!(
-            "Problem getting your username from the environment variables {}: {}",
-            USERNAME, e
-        ),
-    };
-    let sauce_access_key = match env::var(ACCESS_KEY) {
-        Ok(name) => name,
-        Err(e) => panic!(
-            "Problem getting the access key from your environment variables {}: {}",
-            ACCESS_KEY, e
-        ),
-    };
-    let creds: Credentials = Credentials {
+    let sauce_username = env::var(USERNAME).map_err(|_| ShelperError::MissingCredentials {
+        var: USERNAME.to_string(),
+    })?;
+    let sauce_access_key = env::var(ACCESS_KEY).map_err(|_| ShelperError::MissingCredentials {
+        var: ACCESS_KEY.to_string(),
+    })?;
+    Ok(Credentials {
         username: sauce_username,
         access_key: sauce_access_key,
-    };
-    return creds;
+    })
 }
 
 /// mask the API Key used by users prior to outputting it.
 pub fn mask_key(api_key: String) -> String {
-    let mut mask = String::new();
-    let mut i = 0;
-    for c in api_key.chars() {
-        if i == 5 {
-            break;
-        }
-        mask.push(c);
-        i += 1;
-    }
-    return mask;
+    api_key.chars().take(5).collect()
 }
 
 #[cfg(test)]
@@ -79,6 +61,6 @@ mod tests {
     #[ignore]
     fn find_env_variables() {
         super::setup("my.name", "random123125152");
-        assert_eq!(super::env_credentials().username, "my.name");
+        assert_eq!(super::env_credentials().unwrap().username, "my.name");
     }
 }
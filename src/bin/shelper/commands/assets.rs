@@ -0,0 +1,107 @@
+use crate::output::{self, Format};
+use crate::table;
+use shelper::api;
+use shelper::assets::{Asset, Progress};
+use shelper::jobs;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+
+/// `shelper job <id> --download-assets <dir>` saves the assets of the job
+/// into `dir`, or only the ones named in `names`.  Progress goes to stderr,
+/// the saved files are printed to stdout.  Files that are already there are
+/// skipped and interrupted downloads resume, so it can be run again after a
+/// failure.  Exits with 1 if any asset could not be downloaded.
+pub fn download(
+    client: &api::SauceClient,
+    job_id: &str,
+    dir: &Path,
+    names: &[&str],
+    format: Format,
+) {
+    let job = match jobs::JobDetails::new(client, job_id) {
+        Ok(job) => job,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let assets = match job.assets(client) {
+        Ok(assets) => assets,
+        Err(e) => {
+            eprintln!("Problem listing the assets of job {}: {}", job_id, e);
+            std::process::exit(1);
+        }
+    };
+    let mut wanted: Vec<&Asset> = vec![];
+    for name in names {
+        match assets.get(name) {
+            Some(asset) => wanted.push(asset),
+            None => eprintln!("Job {} has no asset {}, skipping", job_id, name),
+        }
+    }
+    if names.is_empty() {
+        wanted = assets.assets.iter().collect();
+    }
+
+    let live = io::stderr().is_terminal();
+    let mut saved = vec![];
+    let mut failed = false;
+    for asset in wanted {
+        let mut last = 0;
+        let mut progress = |p: &Progress| {
+            last = p.downloaded;
+            if live {
+                eprint!("\r{}", progress_line(p));
+            }
+        };
+        match assets.download(client, asset, dir, &mut progress) {
+            Ok(path) => {
+                match live {
+                    true => eprintln!(),
+                    false => eprintln!("{}  {}", asset.file_name, table::size(last)),
+                }
+                saved.push((asset, path));
+            }
+            Err(e) => {
+                if live {
+                    eprintln!();
+                }
+                eprintln!("Problem downloading {}: {}", asset.file_name, e);
+                failed = true;
+            }
+        }
+    }
+
+    let downloaded: Vec<serde_json::Value> = saved
+        .iter()
+        .map(|(asset, path)| {
+            serde_json::json!({
+                "kind": asset.kind,
+                "file_name": asset.file_name,
+                "path": path.display().to_string(),
+            })
+        })
+        .collect();
+    if !output::records(format, &downloaded) {
+        for (_, path) in saved.iter() {
+            println!("{}", path.display());
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// `video.mp4  12.3 MB / 40.1 MB (30%)`
+fn progress_line(p: &Progress) -> String {
+    match p.total {
+        Some(total) if total > 0 => format!(
+            "{}  {} / {} ({}%)  ",
+            p.asset.file_name,
+            table::size(p.downloaded),
+            table::size(total),
+            p.downloaded * 100 / total
+        ),
+        _ => format!("{}  {}  ", p.asset.file_name, table::size(p.downloaded)),
+    }
+}
@@ -0,0 +1,131 @@
+use crate::output::{self, Format};
+use crate::table;
+use shelper::api;
+use shelper::builds;
+use shelper::render::Render;
+use std::io;
+use std::time::Duration;
+
+/// `shelper build <id>` prints the build summary and lists every job in it.
+/// With `junit` the jobs are also written to that file as a JUnit XML report.
+/// With `watch` it first waits up to that long for the build to finish,
+/// printing the job counters as they change, and exits with 1 if any job
+/// failed or errored.  json and yaml print the build with its jobs,
+/// ndjson and csv only the jobs.
+pub fn show(
+    client: &api::SauceClient,
+    build_id: &str,
+    junit: Option<&str>,
+    watch: Option<Duration>,
+    format: Format,
+) {
+    let build = match watch {
+        Some(timeout) => builds::BuildWatch::new(client, build_id)
+            .timeout(timeout)
+            .wait(|build| {
+                let jobs = &build.jobs;
+                eprintln!(
+                    "[{}] {}: {} queued, {} running, {} passed, {} failed, {} errored",
+                    chrono::Local::now().format("%H:%M:%S"),
+                    build.status,
+                    jobs.queued,
+                    jobs.running,
+                    jobs.passed,
+                    jobs.failed,
+                    jobs.errored
+                )
+            }),
+        None => builds::Build::new(client, build_id),
+    };
+    let build = match build {
+        Ok(build) => build,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let jobs = match build.jobs(client) {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            eprintln!("Problem getting the jobs in build {}: {}", build_id, e);
+            std::process::exit(1);
+        }
+    };
+    match format {
+        Format::Json | Format::Yaml => {
+            output::document(
+                format,
+                &serde_json::json!({ "build": &build, "jobs": &jobs.jobs }),
+            );
+        }
+        Format::Ndjson | Format::Csv => {
+            output::records(format, &jobs.jobs);
+        }
+        Format::Pretty | Format::Table => print_jobs(&build, &jobs),
+    }
+
+    if let Some(path) = junit {
+        if let Err(e) = std::fs::write(path, build.junit(&jobs)) {
+            eprintln!("Problem writing the JUnit report to {}: {}", path, e);
+            std::process::exit(1);
+        }
+        eprintln!("Wrote the JUnit report to {}", path);
+    }
+
+    if watch.is_some() && build.has_failures() {
+        std::process::exit(1);
+    }
+}
+
+fn print_jobs(build: &builds::Build, jobs: &builds::BuildJobs) {
+    let _ = build.render(output::pretty(), &mut io::stdout());
+    println!();
+    let rows: Vec<Vec<String>> = jobs
+        .iter()
+        .map(|job| {
+            vec![
+                job.name.clone().unwrap_or_else(|| "-".to_string()),
+                job.result().to_string(),
+                job.platform(),
+                table::duration(job.duration()),
+                job.link(),
+            ]
+        })
+        .collect();
+    table::print(&["NAME", "STATUS", "PLATFORM", "DURATION", "LINK"], &rows);
+}
+
+/// `shelper builds` prints one row per build matching `query`
+pub fn list(client: &api::SauceClient, query: &builds::BuildQuery, format: Format) {
+    let found = match query
+        .fetch(client)
+        .collect::<shelper::sauce_errors::Result<Vec<_>>>()
+    {
+        Ok(found) => found,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if output::records(format, &found) {
+        return;
+    }
+    let mut rows = vec![];
+    for build in found {
+        rows.push(vec![
+            build.id.clone(),
+            build.name.clone().unwrap_or_else(|| "-".to_string()),
+            build.status.clone(),
+            format!("{}/{}", build.jobs.passed, build.jobs.finished),
+            table::time(Some(build.start_time as i64)),
+            table::duration(build.duration().map(|secs| secs as i64)),
+            build.owner.clone(),
+        ]);
+    }
+    table::print(
+        &[
+            "ID", "NAME", "STATUS", "PASSED", "STARTED", "DURATION", "OWNER",
+        ],
+        &rows,
+    );
+}
@@ -0,0 +1,71 @@
+use crate::output::{self, Format};
+use crate::table;
+use shelper::api;
+use shelper::compare::{BuildComparison, Change};
+
+/// `shelper compare <base> <head>` prints what changed between two builds,
+/// regressions first.  Tests that passed in both are only listed with `all`.
+/// json and yaml print the whole report, ndjson and csv one test per line.
+pub fn builds(
+    client: &api::SauceClient,
+    base_build: &str,
+    head_build: &str,
+    all: bool,
+    format: Format,
+) {
+    let report = match BuildComparison::new(client, base_build, head_build) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let printed = match format {
+        Format::Ndjson | Format::Csv => output::records(format, &report.changes),
+        _ => output::document(format, &report),
+    };
+    if printed {
+        return;
+    }
+    let order = [
+        Change::NewlyFailing,
+        Change::StillFailing,
+        Change::NewlyPassing,
+        Change::Added,
+        Change::Removed,
+        Change::StillPassing,
+    ];
+    let summary: Vec<String> = order
+        .iter()
+        .map(|change| format!("{} {}", report.count(*change), change))
+        .collect();
+    println!("{} -> {}", base_build, head_build);
+    println!("{}", summary.join(", "));
+    println!();
+
+    let status = |job: &Option<shelper::builds::BuildJob>| match job {
+        Some(job) => job.result().to_string(),
+        None => "-".to_string(),
+    };
+    let mut rows = vec![];
+    for change in order {
+        if change == Change::StillPassing && !all {
+            continue;
+        }
+        for c in report.with(change) {
+            let link = c.head.as_ref().or(c.base.as_ref()).map(|job| job.link());
+            rows.push(vec![
+                change.to_string(),
+                c.name.clone(),
+                c.platform.clone(),
+                status(&c.base),
+                status(&c.head),
+                link.unwrap_or_default(),
+            ]);
+        }
+    }
+    table::print(
+        &["CHANGE", "NAME", "PLATFORM", "BASE", "HEAD", "LINK"],
+        &rows,
+    );
+}
@@ -0,0 +1,59 @@
+use crate::output::{self, Format};
+use crate::table;
+use shelper::api;
+use shelper::flaky;
+use shelper::jobs;
+
+/// `shelper flaky` ranks the tests in `query` that both passed and failed,
+/// printing at most `limit` of them
+pub fn list(
+    client: &api::SauceClient,
+    query: &jobs::JobQuery,
+    min_runs: usize,
+    limit: usize,
+    format: Format,
+) {
+    let tests = match flaky::from_history(client, query, min_runs) {
+        Ok(tests) => tests,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if output::records(format, &tests[..tests.len().min(limit)]) {
+        return;
+    }
+    if tests.is_empty() {
+        println!("No flaky tests found");
+        return;
+    }
+    let rows: Vec<Vec<String>> = tests
+        .iter()
+        .take(limit)
+        .map(|test| {
+            vec![
+                test.name.clone(),
+                test.platform.clone(),
+                test.runs.to_string(),
+                test.passes.to_string(),
+                test.failures.to_string(),
+                format!("{:.0}%", test.flip_rate * 100.0),
+                test.passing_job.clone(),
+                test.failing_job.clone(),
+            ]
+        })
+        .collect();
+    table::print(
+        &[
+            "NAME",
+            "PLATFORM",
+            "RUNS",
+            "PASSED",
+            "FAILED",
+            "FLIP RATE",
+            "PASSING JOB",
+            "FAILING JOB",
+        ],
+        &rows,
+    );
+}
@@ -0,0 +1,326 @@
+use crate::input_stripper;
+use crate::output::{self, Format};
+use crate::table;
+use shelper::api;
+use shelper::builds;
+use shelper::jobs;
+use shelper::render::Render;
+use shelper::sauce_errors::ShelperError;
+use shelper::triage;
+use std::collections::HashSet;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+const HEADERS: [&str; 7] = [
+    "ID", "NAME", "STATUS", "PLATFORM", "STARTED", "DURATION", "TUNNEL",
+];
+
+/// `shelper -j <job>...` looks up each job and prints the ones matching
+/// the filters in `query`
+pub fn show(client: &api::SauceClient, job_ids: &[String], query: &jobs::JobQuery, format: Format) {
+    let job_count = job_ids.len();
+    let mut found = vec![];
+    for (i, job) in job_ids.iter().enumerate() {
+        let deets = match jobs::JobDetails::new(client, job) {
+            Ok(deets) => deets,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+        if !query.matches(&deets) {
+            eprintln!(
+                "{}/{} {} does not match the filters, skipping",
+                i + 1,
+                job_count,
+                job
+            );
+            continue;
+        }
+        if format == Format::Pretty {
+            println!("{}/{}", i + 1, job_count);
+            let _ = deets.render(output::pretty(), &mut io::stdout());
+            println!();
+        } else {
+            found.push(deets);
+        }
+    }
+    if format == Format::Table {
+        let rows: Vec<Vec<String>> = found.iter().map(row).collect();
+        table::print(&HEADERS, &rows);
+    } else {
+        output::records(format, &found);
+    }
+}
+
+/// `shelper jobs recent` lists the jobs matching `query`, one row per job.
+/// Exits with 1 without printing any jobs if the API returns an error.
+/// Warns when the search stopped at the scan limit, the jobs found so far
+/// are still printed.
+pub fn recent(client: &api::SauceClient, query: jobs::JobQuery, format: Format) {
+    let mut found = vec![];
+    for job in query.fetch(client) {
+        match job {
+            Ok(job) => found.push(job),
+            Err(e @ ShelperError::ScanLimitReached { .. }) => eprintln!("{} (--since)", e),
+            Err(e) => {
+                eprintln!("Problem getting jobs for {}: {}", query.owner(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if !output::records(format, &found) {
+        let rows: Vec<Vec<String>> = found.iter().map(row).collect();
+        table::print(&HEADERS, &rows);
+    }
+}
+
+/// `shelper job <job> --why` prints what kind of failure the job had,
+/// the evidence for it and what to try next
+pub fn why(client: &api::SauceClient, job_id: &str, format: Format) {
+    let diagnosis =
+        jobs::JobDetails::new(client, job_id).and_then(|job| triage::Diagnosis::new(client, &job));
+    let diagnosis = match diagnosis {
+        Ok(diagnosis) => diagnosis,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if !output::document(format, &diagnosis) {
+        let _ = diagnosis.render(output::pretty(), &mut io::stdout());
+    }
+}
+
+/// `shelper job update` applies `update` to each of `job_ids`, or to every
+/// job matching `query` when no ids are given, and prints what changed.
+/// With `dry_run` nothing is sent.  Before updating every job matching
+/// `query` it lists them and asks, unless `yes`.  Exits with 1 if any job
+/// could not be updated.
+pub fn update(
+    client: &api::SauceClient,
+    job_ids: &[String],
+    query: &jobs::JobQuery,
+    update: &jobs::JobUpdate,
+    dry_run: bool,
+    yes: bool,
+    format: Format,
+) {
+    let found: Vec<shelper::sauce_errors::Result<jobs::JobDetails>> = match job_ids.is_empty() {
+        true => confirmed_matches(client, query, dry_run || yes, "Update")
+            .into_iter()
+            .map(Ok)
+            .collect(),
+        false => job_ids
+            .iter()
+            .map(|id| jobs::JobDetails::new(client, id))
+            .collect(),
+    };
+    let mut updated = vec![];
+    let mut failed = false;
+    for job in found {
+        let mut job = match job {
+            Ok(job) => job,
+            Err(e) => {
+                eprintln!("{}", e);
+                failed = true;
+                continue;
+            }
+        };
+        let changes = match job.update(client, update, dry_run) {
+            Ok(changes) => changes,
+            Err(e) => {
+                eprintln!("Problem updating {}: {}", job.id, e);
+                failed = true;
+                continue;
+            }
+        };
+        if format == Format::Pretty || format == Format::Table {
+            println!("{}", describe(&job.id, &changes, dry_run));
+        }
+        updated.push(serde_json::json!({
+            "job_id": job.id,
+            "applied": !dry_run && !changes.is_empty(),
+            "changes": changes,
+        }));
+    }
+    output::records(format, &updated);
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// `shelper job stop <job>...` stops each job.  Exits with 1 if any job
+/// could not be stopped.
+pub fn stop(client: &api::SauceClient, job_ids: &[String], format: Format) {
+    let mut found = vec![];
+    let mut failed = false;
+    for id in job_ids {
+        match jobs::JobDetails::new(client, id) {
+            Ok(job) => found.push(job),
+            Err(e) => {
+                eprintln!("{}", e);
+                failed = true;
+            }
+        }
+    }
+    if !stop_each(client, found, format) || failed {
+        std::process::exit(1);
+    }
+}
+
+/// `shelper job stop --all|--build|--tunnel` stops the owner's running jobs
+/// that match `query`, looking back `jobs::RUNNING_LOOKBACK`.  `build` is a
+/// build id, a build URL or a build name.  Lists the jobs on stderr and asks first,
+/// unless `yes`.
+pub fn stop_running(
+    client: &api::SauceClient,
+    query: &jobs::JobQuery,
+    build: Option<&str>,
+    yes: bool,
+    format: Format,
+) {
+    let mut query = query.clone();
+    let mut in_build = None;
+    if let Some(build) = build {
+        match jobs_in_build(client, build) {
+            Some(ids) => in_build = Some(ids),
+            None => query = query.build(build),
+        }
+    }
+    let since = chrono::Utc::now().timestamp() - jobs::RUNNING_LOOKBACK;
+    let recent = match jobs::BulkFullJobs::since(client, query.owner(), since) {
+        Ok(recent) => recent,
+        Err(e) => {
+            eprintln!("Problem getting jobs for {}: {}", query.owner(), e);
+            std::process::exit(1);
+        }
+    };
+    let running: Vec<jobs::JobDetails> = recent
+        .running()
+        .filter(|job| query.matches(job))
+        .filter(|job| in_build.as_ref().is_none_or(|ids| ids.contains(&job.id)))
+        .collect();
+    if running.is_empty() {
+        eprintln!("No running jobs to stop");
+        return;
+    }
+    if !yes {
+        let rows: Vec<Vec<String>> = running.iter().map(row).collect();
+        table::eprint(&HEADERS, &rows);
+        if !confirm(&format!("Stop these {} jobs?", running.len())) {
+            eprintln!("Nothing was stopped");
+            std::process::exit(1);
+        }
+    }
+    if !stop_each(client, running, format) {
+        std::process::exit(1);
+    }
+}
+
+/// The ids of the jobs in the build `build`, when it is the id or URL of a
+/// build.  `None` means `build` is the name of a build.
+fn jobs_in_build(client: &api::SauceClient, build: &str) -> Option<HashSet<String>> {
+    let build_id = input_stripper::get_build_id(build).ok()?;
+    match builds::BuildJobs::new(client, &build_id) {
+        Ok(found) if !found.jobs.is_empty() => Some(found.into_iter().map(|job| job.id).collect()),
+        Ok(_) | Err(ShelperError::NotFound { .. }) | Err(ShelperError::WrongRegion { .. }) => None,
+        Err(e) => {
+            eprintln!("Problem getting the jobs of build {}: {}", build_id, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Stop every job in `found` and print the outcome.  False if any failed.
+fn stop_each(client: &api::SauceClient, found: Vec<jobs::JobDetails>, format: Format) -> bool {
+    let mut stopped = vec![];
+    let mut ok = true;
+    for mut job in found {
+        if let Err(e) = job.stop(client) {
+            eprintln!("Problem stopping {}: {}", job.id, e);
+            ok = false;
+            continue;
+        }
+        if format == Format::Pretty || format == Format::Table {
+            println!("{}: stopped, now {}", job.id, job.consolidated_status);
+        }
+        stopped.push(job);
+    }
+    output::records(format, &stopped);
+    ok
+}
+
+/// Every job matching `query`.  Unless `yes`, lists them on stderr and asks before
+/// going on with `action`, exiting with 1 on a no or when the API errors.
+fn confirmed_matches(
+    client: &api::SauceClient,
+    query: &jobs::JobQuery,
+    yes: bool,
+    action: &str,
+) -> Vec<jobs::JobDetails> {
+    let matched = match query
+        .fetch(client)
+        .collect::<shelper::sauce_errors::Result<Vec<_>>>()
+    {
+        Ok(matched) => matched,
+        Err(e) => {
+            eprintln!("Problem getting jobs for {}: {}", query.owner(), e);
+            std::process::exit(1);
+        }
+    };
+    if matched.is_empty() || yes {
+        return matched;
+    }
+    let rows: Vec<Vec<String>> = matched.iter().map(row).collect();
+    table::eprint(&HEADERS, &rows);
+    if !confirm(&format!("{} these {} jobs?", action, matched.len())) {
+        eprintln!("Nothing was changed");
+        std::process::exit(1);
+    }
+    matched
+}
+
+/// Ask `question` on stderr and read a yes or no from stdin.  Without a
+/// terminal to ask on the answer is no.
+fn confirm(question: &str) -> bool {
+    if !io::stdin().is_terminal() {
+        eprintln!("{} Not asking without a terminal, pass --yes", question);
+        return false;
+    }
+    eprint!("{} [y/N] ", question);
+    let _ = io::stderr().flush();
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// `<id>: passed null -> true, tags [] -> ["nightly"]`
+fn describe(job_id: &str, changes: &[jobs::FieldChange], dry_run: bool) -> String {
+    if changes.is_empty() {
+        return format!("{}: nothing to change", job_id);
+    }
+    let listed: Vec<String> = changes
+        .iter()
+        .map(|c| format!("{} {} -> {}", c.field, c.from, c.to))
+        .collect();
+    match dry_run {
+        true => format!("{}: would change {}", job_id, listed.join(", ")),
+        false => format!("{}: {}", job_id, listed.join(", ")),
+    }
+}
+
+fn row(job: &jobs::JobDetails) -> Vec<String> {
+    vec![
+        job.id.clone(),
+        job.name.clone().unwrap_or_else(|| "-".to_string()),
+        job.consolidated_status.clone(),
+        job.platform(),
+        table::time(job.start_time),
+        table::duration(job.duration()),
+        job.assigned_tunnel_id
+            .clone()
+            .unwrap_or_else(|| "-".to_string()),
+    ]
+}
@@ -0,0 +1,14 @@
+/// `shelper job <job> --download-assets ...`
+pub mod assets;
+/// `shelper build ...` and `shelper builds`
+pub mod build;
+/// `shelper compare ...`
+pub mod compare;
+/// `shelper flaky`
+pub mod flaky;
+/// `shelper -j ...`, `shelper job ...` and `shelper jobs ...`
+pub mod jobs;
+/// `shelper stats`
+pub mod stats;
+/// `shelper -t ...`
+pub mod tunnels;
@@ -0,0 +1,77 @@
+use crate::output::{self, Format};
+use crate::table;
+use shelper::api;
+use shelper::jobs;
+use shelper::stats::{JobStats, Tally};
+use std::collections::BTreeMap;
+
+/// `shelper stats` summarizes the jobs in `query`
+pub fn report(client: &api::SauceClient, query: &jobs::JobQuery, format: Format) {
+    let stats = match JobStats::from_history(client, query) {
+        Ok(stats) => stats,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if output::document(format, &stats) {
+        return;
+    }
+
+    println!("Jobs: {}", stats.total);
+    let statuses: Vec<String> = stats
+        .by_status
+        .iter()
+        .map(|(status, count)| format!("{} {}", count, status))
+        .collect();
+    println!("Status: {}", statuses.join(", "));
+    if let Some(duration) = &stats.duration {
+        println!(
+            "Duration: {} average, {} p50, {} p95, {} max",
+            table::duration(Some(duration.average.round() as i64)),
+            table::duration(Some(duration.p50)),
+            table::duration(Some(duration.p95)),
+            table::duration(Some(duration.max))
+        );
+    }
+    println!(
+        "Proxied: {} ({:.0}%)  Tunneled: {} ({:.0}%)",
+        stats.proxied,
+        stats.proxied_share() * 100.0,
+        stats.tunneled,
+        stats.tunneled_share() * 100.0
+    );
+
+    breakdown("BROWSER", &stats.by_browser);
+    breakdown("OS", &stats.by_os);
+    breakdown("AUTOMATION", &stats.by_automation_backend);
+
+    if !stats.errors.is_empty() {
+        println!();
+        let rows: Vec<Vec<String>> = stats
+            .errors
+            .iter()
+            .map(|e| vec![e.jobs.to_string(), e.error.clone()])
+            .collect();
+        table::print(&["JOBS", "ERROR"], &rows);
+    }
+}
+
+/// One table row per group, biggest first
+fn breakdown(name: &str, groups: &BTreeMap<String, Tally>) {
+    let mut groups: Vec<(&String, &Tally)> = groups.iter().collect();
+    groups.sort_by_key(|(_, tally)| std::cmp::Reverse(tally.jobs));
+    let rows: Vec<Vec<String>> = groups
+        .iter()
+        .map(|(group, tally)| {
+            vec![
+                group.to_string(),
+                tally.jobs.to_string(),
+                tally.passed.to_string(),
+                tally.failed.to_string(),
+            ]
+        })
+        .collect();
+    println!();
+    table::print(&[name, "JOBS", "PASSED", "FAILED"], &rows);
+}
@@ -0,0 +1,52 @@
+use crate::output::{self, Format};
+use crate::table;
+use shelper::api;
+use shelper::render::Render;
+use shelper::tunnels;
+use std::io;
+
+/// `shelper -t <tunnel>...` looks up each tunnel started by `owner`
+pub fn show(client: &api::SauceClient, owner: &str, tunnel_ids: &[&str], format: Format) {
+    let tunnel_count = tunnel_ids.len();
+    let mut found = vec![];
+    for (i, tunnel) in tunnel_ids.iter().enumerate() {
+        let info = match tunnels::TunnelMetadata::new(client, owner, tunnel) {
+            Ok(info) => info,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+        if format == Format::Pretty {
+            println!("{}/{}", i + 1, tunnel_count);
+            let _ = info.render(output::pretty(), &mut io::stdout());
+            println!();
+        } else {
+            found.push(info);
+        }
+    }
+    if format == Format::Table {
+        let rows: Vec<Vec<String>> = found
+            .iter()
+            .map(|tunnel| {
+                vec![
+                    tunnel.id.clone(),
+                    tunnel.name.clone().unwrap_or_else(|| "-".to_string()),
+                    tunnel.owner.clone(),
+                    tunnel.status.clone(),
+                    tunnel.region.to_string(),
+                    tunnel.metadata.release.clone(),
+                    table::time(tunnel.creation_time),
+                ]
+            })
+            .collect();
+        table::print(
+            &[
+                "ID", "NAME", "OWNER", "STATUS", "REGION", "VERSION", "STARTED",
+            ],
+            &rows,
+        );
+    } else {
+        output::records(format, &found);
+    }
+}
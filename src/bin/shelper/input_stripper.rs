@@ -1,3 +1,6 @@
+use chrono::{DateTime, NaiveDate};
+use std::convert::TryFrom;
+use std::time::Duration;
 use url::{ParseError, Url};
 
 /// `get_session_id` strips the session id from a URL. If the user
@@ -18,15 +21,15 @@ fn get_session_id(user_arg: &str) -> Result<String, String> {
     if path.len() != 3 {
         // is it too short to be a session id?
         if user_arg.len() <= 20 {
-            return Err(format!("Invalid Url or session id: {}", user_arg))?;
+            return Err(format!("Invalid Url or session id: {}", user_arg));
         }
         // return the literal session id (if it is valid)
         match path.len() {
             1 => return Ok(user_arg.to_string()),
-            _ => return Err(format!("Invalid Url or session id: {}", user_arg))?,
+            _ => return Err(format!("Invalid Url or session id: {}", user_arg)),
         }
     }
-    return Ok(path[2].to_string());
+    Ok(path[2].to_string())
 }
 
 /// get_job_id returns a vector of sanitized job ids. It accepts the raw vector of
@@ -45,6 +48,82 @@ pub fn get_job_id(jobs: Vec<&str>) -> Vec<String> {
     job_ids
 }
 
+/// get_build_id returns the build id from a build URL like
+/// `https://app.saucelabs.com/builds/vdc/<build-id>`, or the literal build id.
+pub fn get_build_id(user_arg: &str) -> Result<String, String> {
+    let with_scheme = match user_arg.contains("://") {
+        true => user_arg.to_string(),
+        false if user_arg.contains('/') => format!("https://{}", user_arg),
+        false => user_arg.to_string(),
+    };
+    if let Ok(build_url) = Url::parse(&with_scheme) {
+        return match build_url
+            .path_segments()
+            .and_then(|mut segments| segments.rfind(|s| !s.is_empty()))
+        {
+            Some(id) if build_url.path().contains("/builds/") => Ok(id.to_string()),
+            _ => Err(format!("Invalid build Url: {}", user_arg)),
+        };
+    }
+    if user_arg.len() <= 20 || !user_arg.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("Invalid Url or build id: {}", user_arg));
+    }
+    Ok(user_arg.to_string())
+}
+
+/// `parse_time` turns a user supplied point in time into a unix timestamp.
+/// Accepts durations before `now` like `30m`, `12h`, `7d` or `2w`,
+/// a date `2020-04-10`, an RFC 3339 timestamp, or a unix timestamp.
+pub fn parse_time(user_arg: &str, now: i64) -> Result<i64, String> {
+    let arg = user_arg.trim();
+    if let Ok(timestamp) = arg.parse::<i64>() {
+        return Ok(timestamp);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(arg) {
+        return Ok(time.timestamp());
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(arg, "%Y-%m-%d") {
+        if let Some(midnight) = date.and_hms_opt(0, 0, 0) {
+            return Ok(midnight.and_utc().timestamp());
+        }
+    }
+    match parse_duration(arg)
+        .ok()
+        .and_then(|ago| i64::try_from(ago.as_secs()).ok())
+        .and_then(|ago| now.checked_sub(ago))
+    {
+        Some(time) => Ok(time),
+        None => Err(format!("Invalid time: {}", user_arg)),
+    }
+}
+
+/// `parse_duration` reads an amount of time like `90s`, `30m`, `12h`, `7d`
+/// or `2w`.  A bare number is seconds.
+pub fn parse_duration(user_arg: &str) -> Result<Duration, String> {
+    let arg = user_arg.trim();
+    let (amount, unit) = match arg.char_indices().last() {
+        Some((_, c)) if c.is_ascii_digit() => (arg, 's'),
+        Some((at, c)) => (&arg[..at], c),
+        None => return Err(format!("Invalid duration: {}", user_arg)),
+    };
+    let seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return Err(format!("Invalid duration: {}", user_arg)),
+    };
+    match amount
+        .parse::<u64>()
+        .ok()
+        .and_then(|a| a.checked_mul(seconds))
+    {
+        Some(secs) => Ok(Duration::from_secs(secs)),
+        None => Err(format!("Invalid duration: {}", user_arg)),
+    }
+}
+
 #[test]
 fn get_session_id_short_url() {
     let short_url = "https://app.saucelabs.com/tests/d73e717d7fcd46cd9927f369ba64fc28".to_string();
@@ -87,3 +166,59 @@ fn get_bulk_job_id() {
         ]
     )
 }
+
+#[test]
+fn parse_relative_and_absolute_times() {
+    let now = 1_600_000_000;
+    assert_eq!(parse_time("7d", now).unwrap(), now - 7 * 24 * 60 * 60);
+    assert_eq!(parse_time("90m", now).unwrap(), now - 90 * 60);
+    assert_eq!(parse_time("1586477711", now).unwrap(), 1586477711);
+    assert_eq!(parse_time("2020-04-10", now).unwrap(), 1586476800);
+    assert_eq!(
+        parse_time("2020-04-10T00:15:11+00:00", now).unwrap(),
+        1586477711
+    );
+    assert!(parse_time("last tuesday", now).is_err());
+    assert!(parse_time("d", now).is_err());
+    assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+    assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+    assert!(parse_duration("2y").is_err());
+}
+
+#[test]
+fn parse_duration_rejects_non_ascii_unit() {
+    assert!(parse_duration("7д").is_err());
+    assert!(parse_duration("д").is_err());
+    assert!(parse_duration("").is_err());
+    assert!(parse_time("7д", 1_600_000_000).is_err());
+}
+
+#[test]
+fn parse_duration_rejects_overflow() {
+    assert!(parse_duration("99999999999999999w").is_err());
+    assert!(parse_duration("18446744073709551615").is_ok());
+    assert!(parse_duration("18446744073709551615m").is_err());
+    assert!(parse_time("18446744073709551615s", 1_600_000_000).is_err());
+}
+
+#[test]
+fn get_build_id_from_url_or_id() {
+    assert_eq!(
+        get_build_id("https://app.saucelabs.com/builds/vdc/91ee45d589ce4177981bf22f911f22c5/")
+            .unwrap(),
+        "91ee45d589ce4177981bf22f911f22c5"
+    );
+    assert_eq!(
+        get_build_id("app.eu-central-1.saucelabs.com/builds/91ee45d589ce4177981bf22f911f22c5")
+            .unwrap(),
+        "91ee45d589ce4177981bf22f911f22c5"
+    );
+    assert_eq!(
+        get_build_id("91ee45d589ce4177981bf22f911f22c5").unwrap(),
+        "91ee45d589ce4177981bf22f911f22c5"
+    );
+    assert!(
+        get_build_id("https://app.saucelabs.com/tests/91ee45d589ce4177981bf22f911f22c5").is_err()
+    );
+    assert!(get_build_id("junk").is_err());
+}
@@ -1,12 +1,16 @@
 #[macro_use]
 extern crate clap;
 extern crate shelper;
-use clap::{App, Arg};
+use clap::{App, AppSettings, Arg, ArgGroup, ArgMatches, SubCommand};
 use shelper::api;
+use shelper::builds;
 use shelper::jobs;
-use shelper::tunnels;
 use shelper::users;
+use std::path::Path;
+mod commands;
 mod input_stripper;
+mod output;
+mod table;
 
 fn main() {
     let cmds = App::new("shelper")
@@ -39,6 +43,7 @@ If you owned the tunnel and have your Sauce credentials saved as environment var
         )
         .arg(
             Arg::with_name("owner")
+                .global(true)
                 .help("Sauce account that owns a sauce resource (tunnel, job, asset)")
                 .long("owner")
                 .short("o")
@@ -48,6 +53,7 @@ If you owned the tunnel and have your Sauce credentials saved as environment var
         )
         .arg(
             Arg::with_name("access_key")
+                .global(true)
                 .help("Sauce Access Key")
                 .short("k")
                 .long("key")
@@ -57,13 +63,80 @@ If you owned the tunnel and have your Sauce credentials saved as environment var
         )
         .arg(
             Arg::with_name("region")
-                .help("Region/datacenter to search.")
+                .global(true)
+                .help("Region/datacenter to search. \"auto\" tries your home region first, then every other datacenter.")
                 .short("r")
                 .long("region")
                 .takes_value(true)
                 .value_name("region")
-                .possible_value("EU")
-                .possible_value("US"),
+                .case_insensitive(true)
+                .possible_values(&[
+                    "US",
+                    "EU",
+                    "us-west-1",
+                    "us-east-1",
+                    "us-east-4",
+                    "eu-central-1",
+                    "apac-southeast-1",
+                    "headless",
+                    "APAC",
+                    "auto",
+                ]),
+        )
+        .arg(
+            Arg::with_name("base_url")
+                .global(true)
+                .help("Send every request to this url instead of saucelabs.com, ex. a proxy or a mock Sauce API. Can also be set with the SHELPER_BASE_URL environment variable.")
+                .long("base-url")
+                .value_name("url")
+                .takes_value(true)
+                .multiple(false),
+        )
+        .arg(
+            Arg::with_name("output")
+                .global(true)
+                .help("How to print the results. json, yaml, ndjson and csv are for scripts, table prints one row per result, pretty is the default")
+                .long("output")
+                .value_name("format")
+                .takes_value(true)
+                .case_insensitive(true)
+                .possible_values(&["pretty", "table", "json", "ndjson", "csv", "yaml"]),
+        )
+        .arg(
+            Arg::with_name("since")
+                .global(true)
+                .help("Only jobs created after this time. A duration ago (30m, 12h, 7d, 2w), a date (2020-04-10), RFC 3339 or a unix timestamp")
+                .long("since")
+                .value_name("time")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("until")
+                .global(true)
+                .help("Only jobs created before this time. Same formats as --since")
+                .long("until")
+                .value_name("time")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("status")
+                .global(true)
+                .help("Only jobs with this status")
+                .long("status")
+                .value_name("status")
+                .multiple(true)
+                .takes_value(true)
+                .number_of_values(1)
+                .case_insensitive(true)
+                .possible_values(&["passed", "failed", "error", "complete", "in-progress"]),
+        )
+        .arg(
+            Arg::with_name("browser")
+                .global(true)
+                .help("Only jobs run on this browser, ex. chrome")
+                .long("browser")
+                .value_name("browser")
+                .takes_value(true),
         )
         .arg(
             Arg::with_name("tunnel")
@@ -77,91 +150,585 @@ If you owned the tunnel and have your Sauce credentials saved as environment var
                 .multiple(true)
                 .takes_value(true)
         )
+        .subcommand(
+            SubCommand::with_name("jobs")
+                .about("Look through the jobs run by the owner")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("recent")
+                        .about("List recent jobs, one row per job. Takes the --since, --until, --status and --browser filters")
+                        .arg(
+                            Arg::with_name("limit")
+                                .help("Most jobs to list")
+                                .short("l")
+                                .long("limit")
+                                .value_name("count")
+                                .takes_value(true)
+                                .default_value("20"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("job")
+                .about("Details of a single job, why it failed with --why, or its logs, video and screenshots with --download-assets")
+                .arg(
+                    Arg::with_name("job")
+                        .help("Job id or a URL link to the job")
+                        .value_name("job")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("why")
+                        .help("Explain why the job failed from its error and log.json, with next steps")
+                        .long("why")
+                        .takes_value(false)
+                        .conflicts_with("download_assets"),
+                )
+                .arg(
+                    Arg::with_name("download_assets")
+                        .help("Save the job's assets (selenium-server.log, log.json, video.mp4, screenshots.zip, HAR and Appium logs) into this directory. Run it again to resume an interrupted download")
+                        .long("download-assets")
+                        .value_name("dir")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("asset")
+                        .help("Only download this asset, by file name or kind, ex. video.mp4 or sauce-log")
+                        .long("asset")
+                        .value_name("name")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .takes_value(true)
+                        .requires("download_assets"),
+                )
+                .setting(AppSettings::SubcommandsNegateReqs)
+                .subcommand(
+                    SubCommand::with_name("update")
+                        .about("Change the status, name, build, tags or custom data of jobs. Takes job ids, or updates every job matching the --since, --until, --status and --browser filters")
+                        .arg(
+                            Arg::with_name("jobs")
+                                .help("Job ids or URL links to the jobs")
+                                .value_name("job")
+                                .multiple(true)
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("passed")
+                                .help("Mark the jobs passed or failed")
+                                .long("passed")
+                                .value_name("true|false")
+                                .takes_value(true)
+                                .possible_values(&["true", "false"]),
+                        )
+                        .arg(
+                            Arg::with_name("name")
+                                .help("Rename the jobs")
+                                .long("name")
+                                .value_name("name")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("build")
+                                .help("Move the jobs into this build")
+                                .long("build")
+                                .value_name("build")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("tags")
+                                .help("Replace the tags of the jobs, comma separated")
+                                .long("tags")
+                                .value_name("tags")
+                                .takes_value(true)
+                                .use_delimiter(true),
+                        )
+                        .arg(
+                            Arg::with_name("add_tag")
+                                .help("Add a tag to the jobs, keeping the ones they have")
+                                .long("add-tag")
+                                .value_name("tag")
+                                .multiple(true)
+                                .number_of_values(1)
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("custom_data")
+                                .help("Replace the custom data of the jobs with this JSON object")
+                                .long("custom-data")
+                                .value_name("json")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("dry_run")
+                                .help("Only print what would change")
+                                .long("dry-run")
+                                .takes_value(false),
+                        )
+                        .arg(
+                            Arg::with_name("yes")
+                                .help("Don't ask before updating every job that matches the filters")
+                                .short("y")
+                                .long("yes")
+                                .takes_value(false),
+                        )
+                        .group(
+                            ArgGroup::with_name("changes")
+                                .args(&["passed", "name", "build", "tags", "add_tag", "custom_data"])
+                                .multiple(true)
+                                .required(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("stop")
+                        .about("Stop running jobs, ex. sessions left behind by a CI runner that died. Takes job ids, or --all, --build or --tunnel to stop every running job of the owner started in the last day")
+                        .arg(
+                            Arg::with_name("jobs")
+                                .help("Job ids or URL links to the jobs")
+                                .value_name("job")
+                                .multiple(true)
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("all")
+                                .help("Stop every running job of the owner, or only the ones matching --since, --until, --status and --browser")
+                                .long("all")
+                                .takes_value(false),
+                        )
+                        .arg(
+                            Arg::with_name("build")
+                                .help("Stop the running jobs in the build with this id, URL or name")
+                                .long("build")
+                                .value_name("build")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("tunnel")
+                                .help("Stop the running jobs using the tunnel with this id")
+                                .long("tunnel")
+                                .value_name("tunnel_id")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("yes")
+                                .help("Don't ask before stopping every running job that matches")
+                                .short("y")
+                                .long("yes")
+                                .takes_value(false),
+                        )
+                        .group(
+                            ArgGroup::with_name("which")
+                                .args(&["jobs", "all", "build", "tunnel"])
+                                .required(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("build")
+                .about("Summary of a build and every job in it")
+                .arg(
+                    Arg::with_name("build")
+                        .help("Build id or a URL link to the build")
+                        .value_name("build")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("junit")
+                        .help("Also write the jobs to this file as a JUnit XML report")
+                        .long("junit")
+                        .value_name("file")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("watch")
+                        .help("Wait for the build to finish, showing the job counters as they change. Exits with 1 if any job failed or errored")
+                        .long("watch")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("timeout")
+                        .help("Give up watching after this long, ex. 90s, 30m, 2h. Defaults to 1h")
+                        .long("timeout")
+                        .value_name("duration")
+                        .takes_value(true)
+                        .requires("watch"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("compare")
+                .about("What broke or got fixed between two builds. Jobs are matched by name, os, browser and browser version")
+                .arg(
+                    Arg::with_name("base")
+                        .help("Build id or URL to compare against, ex. last night's build")
+                        .value_name("base")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("head")
+                        .help("Build id or URL of the newer build")
+                        .value_name("head")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("all")
+                        .help("Also list the tests that passed in both builds")
+                        .long("all")
+                        .takes_value(false),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("flaky")
+                .about("Tests that both passed and failed on the same platform, flakiest first. Looks at the last 7 days unless --since is given, also takes the --until and --browser filters")
+                .arg(
+                    Arg::with_name("min_runs")
+                        .help("Only tests that ran at least this many times")
+                        .long("min-runs")
+                        .value_name("count")
+                        .takes_value(true)
+                        .default_value("3"),
+                )
+                .arg(
+                    Arg::with_name("limit")
+                        .help("Most tests to list")
+                        .short("l")
+                        .long("limit")
+                        .value_name("count")
+                        .takes_value(true)
+                        .default_value("20"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("stats")
+                .about("Health snapshot of the owner's jobs: results, platforms, durations and errors. Looks at the last 7 days unless --since is given, also takes the --until, --status and --browser filters"),
+        )
+        .subcommand(
+            SubCommand::with_name("builds")
+                .about("List recent builds, newest first. Takes the --since, --until and --status filters")
+                .arg(
+                    Arg::with_name("limit")
+                        .help("Most builds to list")
+                        .short("l")
+                        .long("limit")
+                        .value_name("count")
+                        .takes_value(true)
+                        .default_value("20"),
+                )
+                .arg(
+                    Arg::with_name("name")
+                        .help("Only builds whose name starts with this, ex. a branch name")
+                        .long("name")
+                        .value_name("prefix")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("team")
+                        .help("Include the builds of every user under the owner. Needs an admin key")
+                        .long("team")
+                        .takes_value(false),
+                ),
+        )
         .get_matches();
+    // flags can be given before or after a subcommand, they are all
+    // available on the matches of the last subcommand
+    let args = innermost(&cmds);
 
     if cmds.is_present("version") {
         println!("shelper version {}", env!("CARGO_PKG_VERSION"))
     }
 
     // if the user doesn't specify a region default to US
-    let region = match cmds.is_present("region") {
-        true => value_t!(cmds, "region", users::Region).unwrap_or_else(|e| e.exit()),
+    let auto_region = cmds
+        .value_of("region")
+        .is_some_and(|r| r.eq_ignore_ascii_case("auto"));
+    let region = match args.is_present("region") && !auto_region {
+        true => value_t!(args, "region", users::Region).unwrap_or_else(|e| e.exit()),
         false => users::Region::US,
     };
 
-    // Build out a user w/ key + username + region
-    let owner: users::User;
-    if cmds.is_present("owner") && cmds.is_present("access_key") {
-        let key_arg = cmds.value_of("access_key").unwrap().to_string();
-        let owner_arg = cmds.value_of("owner").unwrap().to_string();
-        match region {
-            users::Region::US => owner = users::User::new(Some(owner_arg), Some(key_arg), None),
-            users::Region::EU => {
-                owner = users::User::new(Some(owner_arg), Some(key_arg), Some(users::Region::EU))
+    // Authenticate as the owner when their key is given, otherwise fall back
+    // to the credentials in the environment (ex. a super admin)
+    let owner_arg = args.value_of("owner").map(|o| o.to_string());
+    let auth_user = match args.value_of("access_key") {
+        Some(key) => users::User::new(owner_arg.clone(), Some(key.to_string()), Some(region)),
+        None => users::User::new(None, None, Some(region)),
+    };
+    let auth_user = match auth_user {
+        Ok(user) => user,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let owner = owner_arg.unwrap_or_else(|| auth_user.creds.username.clone());
+    let mut builder = api::SauceClient::builder(auth_user).auto_region(auto_region);
+    if let Some(base_url) = args.value_of("base_url") {
+        builder = builder.base_url(base_url);
+    }
+    let client = match builder.build() {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Problem creating the Sauce API client: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let query = job_query(args, &owner);
+    // possible_values already checked the format is valid
+    let format: output::Format = args.value_of("output").unwrap_or("pretty").parse().unwrap();
+
+    if let ("job", Some(job_cmds)) = cmds.subcommand() {
+        if let ("stop", Some(stop_cmds)) = job_cmds.subcommand() {
+            if stop_cmds.is_present("jobs") {
+                let job_ids =
+                    input_stripper::get_job_id(stop_cmds.values_of("jobs").unwrap().collect());
+                commands::jobs::stop(&client, &job_ids, format);
+                return;
             }
+            // --since, --until, --status and --browser narrow it down further
+            let mut running = query.clone();
+            if let Some(tunnel) = stop_cmds.value_of("tunnel") {
+                running = running.tunnel_id(tunnel);
+            }
+            commands::jobs::stop_running(
+                &client,
+                &running,
+                stop_cmds.value_of("build"),
+                stop_cmds.is_present("yes"),
+                format,
+            );
+            return;
         }
-    } else if cmds.is_present("owner") {
-        owner = users::User::new(
-            Some(cmds.value_of("owner").unwrap().to_string()),
-            None,
-            Some(region),
-        );
-    // println!("new owner {:?}", owner);
-    } else {
-        match region {
-            users::Region::US => owner = users::User::new(None, None, None),
-            users::Region::EU => owner = users::User::new(None, None, Some(users::Region::EU)),
+        if let ("update", Some(update_cmds)) = job_cmds.subcommand() {
+            let job_ids = input_stripper::get_job_id(
+                update_cmds
+                    .values_of("jobs")
+                    .into_iter()
+                    .flatten()
+                    .collect(),
+            );
+            let filtered = ["since", "until", "status", "browser"]
+                .iter()
+                .any(|flag| update_cmds.is_present(flag));
+            if job_ids.is_empty() && !filtered {
+                eprintln!("Give the ids of the jobs to update, or --since, --until, --status or --browser to update every job matching the filters");
+                std::process::exit(1);
+            }
+            commands::jobs::update(
+                &client,
+                &job_ids,
+                &query,
+                &job_update(update_cmds),
+                update_cmds.is_present("dry_run"),
+                update_cmds.is_present("yes"),
+                format,
+            );
+            return;
         }
+        let job_ids = input_stripper::get_job_id(vec![job_cmds.value_of("job").unwrap()]);
+        if job_ids.is_empty() {
+            std::process::exit(1);
+        }
+        match job_cmds.value_of("download_assets") {
+            Some(dir) => {
+                let names: Vec<&str> = job_cmds.values_of("asset").into_iter().flatten().collect();
+                commands::assets::download(&client, &job_ids[0], Path::new(dir), &names, format);
+            }
+            None if job_cmds.is_present("why") => commands::jobs::why(&client, &job_ids[0], format),
+            None => commands::jobs::show(&client, &job_ids, &query, format),
+        }
+        return;
     }
 
-    if let Some(jobs) = cmds.values_of("job") {
-        let sanitized_jobs = input_stripper::get_job_id(jobs.collect());
-        let job_count = sanitized_jobs.len();
-        for (i, job) in sanitized_jobs.iter().enumerate() {
-            let deets: shelper::jobs::JobDetails;
-            if !cmds.is_present("access_key") {
-                let admin = users::User::new(None, None, None);
-                deets = match jobs::JobDetails::new(job, &owner, Some(&admin)) {
-                    Ok(deets) => deets,
-                    Err(e) => {
-                        eprintln!("{}", e);
-                        continue;
-                    }
-                }
-            } else {
-                deets = match jobs::JobDetails::new(job, &owner, Some(&owner)) {
-                    Ok(deets) => deets,
+    if let ("build", Some(build_cmds)) = cmds.subcommand() {
+        let build_id = match input_stripper::get_build_id(build_cmds.value_of("build").unwrap()) {
+            Ok(id) => id,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        let watch = match build_cmds.is_present("watch") {
+            true => {
+                match input_stripper::parse_duration(build_cmds.value_of("timeout").unwrap_or("1h"))
+                {
+                    Ok(timeout) => Some(timeout),
                     Err(e) => {
-                        eprintln!("{}", e);
-                        continue;
+                        eprintln!("--timeout: {}", e);
+                        std::process::exit(1);
                     }
                 }
             }
-            println!("{}/{}", i + 1, job_count);
-            deets.pretty_print();
-            println!("");
+            false => None,
+        };
+        commands::build::show(
+            &client,
+            &build_id,
+            build_cmds.value_of("junit"),
+            watch,
+            format,
+        );
+        return;
+    }
+
+    if let ("flaky", Some(flaky_cmds)) = cmds.subcommand() {
+        let min_runs = value_t!(flaky_cmds, "min_runs", usize).unwrap_or_else(|e| e.exit());
+        let limit = value_t!(flaky_cmds, "limit", usize).unwrap_or_else(|e| e.exit());
+        commands::flaky::list(&client, &last_week(args, query), min_runs, limit, format);
+        return;
+    }
+
+    if let ("stats", Some(_)) = cmds.subcommand() {
+        commands::stats::report(&client, &last_week(args, query), format);
+        return;
+    }
+
+    if let ("compare", Some(compare_cmds)) = cmds.subcommand() {
+        let build_id =
+            |arg: &str| match input_stripper::get_build_id(compare_cmds.value_of(arg).unwrap()) {
+                Ok(id) => id,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+        commands::compare::builds(
+            &client,
+            &build_id("base"),
+            &build_id("head"),
+            compare_cmds.is_present("all"),
+            format,
+        );
+        return;
+    }
+
+    if let ("builds", Some(builds_cmds)) = cmds.subcommand() {
+        let limit = value_t!(builds_cmds, "limit", u64).unwrap_or_else(|e| e.exit());
+        commands::build::list(&client, &build_query(args, &owner).limit(limit), format);
+        return;
+    }
+
+    if let ("jobs", Some(jobs_cmds)) = cmds.subcommand() {
+        if let ("recent", Some(recent_cmds)) = jobs_cmds.subcommand() {
+            let limit = value_t!(recent_cmds, "limit", u64).unwrap_or_else(|e| e.exit());
+            commands::jobs::recent(&client, query.limit(limit), format);
         }
+        return;
+    }
+
+    if let Some(jobs) = cmds.values_of("job") {
+        let sanitized_jobs = input_stripper::get_job_id(jobs.collect());
+        commands::jobs::show(&client, &sanitized_jobs, &query, format);
     }
 
     if let Some(t) = cmds.values_of("tunnel") {
-        // println!("Splitting: {:?}", t);
         let tunnel_list: Vec<&str> = t.collect();
-        let tunnel_count = tunnel_list.len();
-        // println!("{:?}", tunnels)
-        for (i, tunnel) in tunnel_list.iter().enumerate() {
-            if !cmds.is_present("access_key") {
-                let admin = users::User::new(None, None, None);
-                let info: tunnels::TunnelMetadata =
-                    match api::tunnel_raw(&owner, &tunnel, Some(&admin)) {
-                        Ok(resp) => serde_json::from_str(&resp).unwrap(),
-                        Err(e) => {
-                            eprintln!("{}", e);
-                            continue;
-                        }
-                    };
-                println!("{}/{}", i + 1, tunnel_count);
-                info.pretty_print();
+        commands::tunnels::show(&client, &owner, &tunnel_list, format);
+    }
+}
+
+/// The matches of the deepest subcommand given, or the top level matches
+fn innermost<'a>(cmds: &'a ArgMatches<'a>) -> &'a ArgMatches<'a> {
+    match cmds.subcommand() {
+        (_, Some(sub)) => innermost(sub),
+        _ => cmds,
+    }
+}
+
+/// The unix timestamp given to a time flag like --since, exits on a bad time
+fn time_flag(cmds: &clap::ArgMatches, flag: &str) -> Option<i64> {
+    let now = chrono::Utc::now().timestamp();
+    cmds.value_of(flag)
+        .map(|arg| match input_stripper::parse_time(arg, now) {
+            Ok(time) => time,
+            Err(e) => {
+                eprintln!("--{}: {}", flag, e);
+                std::process::exit(1);
+            }
+        })
+}
+
+/// `query` limited to the last 7 days when --since wasn't given
+fn last_week(cmds: &clap::ArgMatches, query: jobs::JobQuery) -> jobs::JobQuery {
+    match cmds.is_present("since") {
+        true => query,
+        false => query.from(chrono::Utc::now().timestamp() - 7 * 24 * 60 * 60),
+    }
+}
+
+/// Build a `JobQuery` for `owner` from the --since, --until, --status and --browser flags
+fn job_query(cmds: &clap::ArgMatches, owner: &str) -> jobs::JobQuery {
+    let mut query = jobs::JobQuery::new(owner);
+    if let Some(since) = time_flag(cmds, "since") {
+        query = query.from(since);
+    }
+    if let Some(until) = time_flag(cmds, "until") {
+        query = query.to(until);
+    }
+    if let Some(statuses) = cmds.values_of("status") {
+        for status in statuses {
+            // possible_values already checked the status is valid
+            query = query.status(status.parse().unwrap());
+        }
+    }
+    if let Some(browser) = cmds.value_of("browser") {
+        query = query.browser(browser);
+    }
+    query
+}
+
+/// Build a `JobUpdate` from the --passed, --name, --build, --tags, --add-tag
+/// and --custom-data flags, exits on bad custom data
+fn job_update(cmds: &clap::ArgMatches) -> jobs::JobUpdate {
+    let mut update = jobs::JobUpdate::new();
+    if let Some(passed) = cmds.value_of("passed") {
+        // possible_values already checked it's true or false
+        update = update.passed(passed == "true");
+    }
+    if let Some(name) = cmds.value_of("name") {
+        update = update.name(name);
+    }
+    if let Some(build) = cmds.value_of("build") {
+        update = update.build(build);
+    }
+    if let Some(tags) = cmds.values_of("tags") {
+        let tags: Vec<&str> = tags.filter(|t| !t.is_empty()).collect();
+        update = update.tags(&tags);
+    }
+    for tag in cmds.values_of("add_tag").into_iter().flatten() {
+        update = update.add_tag(tag);
+    }
+    if let Some(custom_data) = cmds.value_of("custom_data") {
+        match serde_json::from_str::<serde_json::Value>(custom_data) {
+            Ok(data) if data.is_object() => update = update.custom_data(data),
+            _ => {
+                eprintln!("--custom-data: {} is not a JSON object", custom_data);
+                std::process::exit(1);
             }
         }
     }
+    update
+}
+
+/// Build a `BuildQuery` for `owner` from the --since, --until, --status, --name and --team flags
+fn build_query(cmds: &clap::ArgMatches, owner: &str) -> builds::BuildQuery {
+    let mut query = builds::BuildQuery::new(owner).subaccounts(cmds.is_present("team"));
+    if let Some(since) = time_flag(cmds, "since") {
+        query = query.from(since);
+    }
+    if let Some(until) = time_flag(cmds, "until") {
+        query = query.to(until);
+    }
+    if let Some(statuses) = cmds.values_of("status") {
+        for status in statuses {
+            // every job status possible_value maps to a build status
+            query = query.status(status.parse().unwrap());
+        }
+    }
+    if let Some(prefix) = cmds.value_of("name") {
+        query = query.name_prefix(prefix);
+    }
+    query
 }
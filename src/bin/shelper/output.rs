@@ -0,0 +1,215 @@
+use serde::Serialize;
+use serde_json::Value;
+use shelper::render::{Colored, Formatter, Plain};
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
+
+/// How results are printed, picked with the global --output flag
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    /// The human readable layout of each command, the default
+    Pretty,
+    /// One row per result.  Lists of results are tables in pretty mode too.
+    Table,
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    /// One row per result with nested fields as `parent.child` columns
+    Csv,
+    Yaml,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pretty" => Ok(Format::Pretty),
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            "csv" => Ok(Format::Csv),
+            "yaml" => Ok(Format::Yaml),
+            _ => Err(format!("{} is not an output format", s)),
+        }
+    }
+}
+
+/// How pretty output is rendered: colored on a terminal unless NO_COLOR is set
+pub fn pretty() -> &'static dyn Formatter {
+    if io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none() {
+        &Colored
+    } else {
+        &Plain
+    }
+}
+
+/// `records` prints `items` in one of the machine readable formats: a single
+/// array for json and yaml, one line per item for ndjson, one row per item for
+/// csv.  Returns false for the human formats, the caller prints those itself.
+pub fn records<T: Serialize>(format: Format, items: &[T]) -> bool {
+    let values: Vec<Value> = items.iter().map(to_value).collect();
+    match format {
+        Format::Pretty | Format::Table => return false,
+        Format::Json => println!("{}", pretty_json(&Value::from(values))),
+        Format::Yaml => print_yaml(&values),
+        Format::Ndjson => {
+            for value in values {
+                println!("{}", value);
+            }
+        }
+        Format::Csv => print_csv(&values),
+    }
+    true
+}
+
+/// `document` prints a single result, ex. a report, in one of the machine
+/// readable formats.  ndjson puts it on one line and csv in one row.
+/// Returns false for the human formats.
+pub fn document<T: Serialize>(format: Format, doc: &T) -> bool {
+    let value = to_value(doc);
+    match format {
+        Format::Pretty | Format::Table => return false,
+        Format::Json => println!("{}", pretty_json(&value)),
+        Format::Yaml => print_yaml(&value),
+        Format::Ndjson => println!("{}", value),
+        Format::Csv => print_csv(&[value]),
+    }
+    true
+}
+
+fn to_value<T: Serialize>(item: &T) -> Value {
+    match serde_json::to_value(item) {
+        Ok(value) => value,
+        Err(e) => {
+            eprintln!("Problem serializing the output: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn pretty_json(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+fn print_yaml<T: Serialize>(value: &T) {
+    match serde_yaml::to_string(value) {
+        Ok(yaml) => print!("{}", yaml),
+        Err(e) => {
+            eprintln!("Problem writing YAML: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn print_csv(values: &[Value]) {
+    if let Err(e) = write_csv(io::stdout(), values) {
+        eprintln!("Problem writing CSV: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Writes nothing when there are no columns, ex. no results
+fn write_csv<W: io::Write>(out: W, values: &[Value]) -> csv::Result<()> {
+    let (headers, rows) = csv_rows(values);
+    if headers.is_empty() {
+        return Ok(());
+    }
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(&headers)?;
+    for row in &rows {
+        writer.write_record(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// The columns, in the order they first appear, and one row per value
+fn csv_rows(values: &[Value]) -> (Vec<String>, Vec<Vec<String>>) {
+    let mut headers: Vec<String> = vec![];
+    let mut flat_rows = vec![];
+    for value in values {
+        let mut cells = vec![];
+        flatten("", value, &mut cells);
+        for (column, _) in &cells {
+            if !headers.contains(column) {
+                headers.push(column.clone());
+            }
+        }
+        flat_rows.push(cells);
+    }
+    let rows = flat_rows
+        .into_iter()
+        .map(|cells| {
+            headers
+                .iter()
+                .map(|column| {
+                    cells
+                        .iter()
+                        .find(|(c, _)| c == column)
+                        .map(|(_, cell)| cell.clone())
+                        .unwrap_or_default()
+                })
+                .collect()
+        })
+        .collect();
+    (headers, rows)
+}
+
+/// Nested objects become `parent.child` columns.  Lists of plain values are
+/// joined with `;`, lists of objects get a column per index, ex. `errors.0.jobs`.
+fn flatten(prefix: &str, value: &Value, cells: &mut Vec<(String, String)>) {
+    let column = |key: &str| match prefix {
+        "" => key.to_string(),
+        _ => format!("{}.{}", prefix, key),
+    };
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                flatten(&column(key), value, cells);
+            }
+        }
+        Value::Array(items) if items.iter().any(|i| i.is_object() || i.is_array()) => {
+            for (i, item) in items.iter().enumerate() {
+                flatten(&column(&i.to_string()), item, cells);
+            }
+        }
+        Value::Array(items) => {
+            let joined: Vec<String> = items.iter().map(scalar).collect();
+            cells.push((prefix.to_string(), joined.join(";")));
+        }
+        _ => cells.push((prefix.to_string(), scalar(value))),
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[test]
+fn csv_flattens_nested_fields() {
+    let values = vec![
+        serde_json::json!({"id": "a", "jobs": {"passed": 2}, "tags": ["x", "y"]}),
+        serde_json::json!({"id": "b", "name": null, "errors": [{"jobs": 1}]}),
+    ];
+    let (headers, rows) = csv_rows(&values);
+    assert_eq!(
+        headers,
+        vec!["id", "jobs.passed", "tags", "errors.0.jobs", "name"]
+    );
+    assert_eq!(rows[0], vec!["a", "2", "x;y", "", ""]);
+    assert_eq!(rows[1], vec!["b", "", "", "1", ""]);
+}
+
+#[test]
+fn csv_of_no_results_is_empty() {
+    let mut out = vec![];
+    write_csv(&mut out, &[]).unwrap();
+    assert!(out.is_empty());
+    write_csv(&mut out, &[serde_json::json!({"id": "a"})]).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "id\na\n");
+}
@@ -0,0 +1,86 @@
+use chrono::{TimeZone, Utc};
+use std::io::{self, Write};
+
+/// `print` writes `rows` to stdout as columns padded to the widest cell,
+/// with `headers` as the first row.
+pub fn print(headers: &[&str], rows: &[Vec<String>]) {
+    let _ = write(&mut io::stdout(), headers, rows);
+}
+
+/// `eprint` is `print` to stderr, for tables that are not the command's
+/// output, ex. what a prompt asks about
+pub fn eprint(headers: &[&str], rows: &[Vec<String>]) {
+    let _ = write(&mut io::stderr(), headers, rows);
+}
+
+fn write(out: &mut dyn Write, headers: &[&str], rows: &[Vec<String>]) -> io::Result<()> {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+    let header_row: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    writeln!(out, "{}", format_row(&header_row, &widths))?;
+    for row in rows {
+        writeln!(out, "{}", format_row(row, &widths))?;
+    }
+    Ok(())
+}
+
+fn format_row(row: &[String], widths: &[usize]) -> String {
+    let cells: Vec<String> = row
+        .iter()
+        .zip(widths)
+        .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+        .collect();
+    cells.join("  ").trim_end().to_string()
+}
+
+/// A unix timestamp as `2020-04-10 00:15:11` in UTC, or `-`
+pub fn time(timestamp: Option<i64>) -> String {
+    match timestamp.and_then(|t| Utc.timestamp_opt(t, 0).single()) {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => "-".to_string(),
+    }
+}
+
+/// Seconds as `45s`, `2m05s` or `1h02m`, or `-`
+pub fn duration(seconds: Option<i64>) -> String {
+    match seconds {
+        None => "-".to_string(),
+        Some(s) if s < 60 => format!("{}s", s),
+        Some(s) if s < 60 * 60 => format!("{}m{:02}s", s / 60, s % 60),
+        Some(s) => format!("{}h{:02}m", s / 3600, (s % 3600) / 60),
+    }
+}
+
+/// Bytes as `512 B`, `1.5 KB`, `12.3 MB` or `1.2 GB`
+pub fn size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut scaled = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while scaled >= 1024.0 && unit < UNITS.len() - 1 {
+        scaled /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", scaled, UNITS[unit])
+}
+
+#[test]
+fn durations_are_short() {
+    assert_eq!(duration(Some(45)), "45s");
+    assert_eq!(duration(Some(125)), "2m05s");
+    assert_eq!(duration(Some(3720)), "1h02m");
+    assert_eq!(duration(None), "-");
+}
+
+#[test]
+fn sizes_are_short() {
+    assert_eq!(size(512), "512 B");
+    assert_eq!(size(1536), "1.5 KB");
+    assert_eq!(size(12 * 1024 * 1024 + 300 * 1024), "12.3 MB");
+}
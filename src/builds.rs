@@ -1,7 +1,15 @@
 use super::api;
+use super::render::{self, xml_escape, Formatter, Render};
+use super::sauce_errors::{Result, ShelperError};
 use super::users;
+use chrono::{TimeZone, Utc};
 use serde::{Deserialize, Serialize};
-use std::error::Error;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Build {
@@ -13,7 +21,7 @@ pub struct Build {
     pub start_time: u64,
     pub creation_time: u64,
     pub modification_time: u64,
-    pub end_time: u64,
+    pub end_time: Option<u64>,
     pub number: Option<String>,
     pub public: bool,
     pub prefix: Option<String>,
@@ -23,6 +31,8 @@ pub struct Build {
     pub team_id: String,
     pub group_id: Option<String>,
     pub id: String,
+    #[serde(skip)]
+    pub region: users::Region,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -38,10 +48,855 @@ pub struct Jobs {
     pub public: i32,
 }
 
+/// Every job in a build, from the builds/:id/jobs endpoint
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BuildJobs {
+    pub jobs: Vec<BuildJob>,
+}
+
+/// One job in a build.  Lighter than `jobs::JobDetails`, the builds
+/// endpoint only returns the fields below.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BuildJob {
+    pub id: String,
+    pub name: Option<String>,
+    pub status: Option<String>,
+    pub consolidated_status: Option<String>,
+    pub error: Option<String>,
+    pub passed: Option<bool>,
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    pub creation_time: Option<i64>,
+    pub os: Option<String>,
+    pub browser: Option<String>,
+    pub browser_version: Option<String>,
+    pub owner: Option<String>,
+    pub build: Option<String>,
+    #[serde(skip)]
+    pub region: users::Region,
+}
+
+impl BuildJobs {
+    /// Fetch every job in the build `build_id`, searching every region
+    /// when the client has auto region on
+    pub fn new(client: &api::SauceClient, build_id: &str) -> Result<BuildJobs> {
+        let (resp, region) = client.find_build_jobs(build_id)?;
+        BuildJobs::from_response(resp, region)
+    }
+
+    /// Fetch every job in the build `build_id` of `region`
+    pub fn in_region(
+        client: &api::SauceClient,
+        build_id: &str,
+        region: users::Region,
+    ) -> Result<BuildJobs> {
+        BuildJobs::from_response(client.build_jobs_in(build_id, region)?, region)
+    }
+
+    fn from_response(resp: serde_json::Value, region: users::Region) -> Result<BuildJobs> {
+        let mut build_jobs: BuildJobs = serde_json::from_value(resp.clone())
+            .map_err(|e| ShelperError::deserialize(e, &resp.to_string()))?;
+        for job in build_jobs.jobs.iter_mut() {
+            job.region = region;
+        }
+        Ok(build_jobs)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, BuildJob> {
+        self.jobs.iter()
+    }
+
+    /// Jobs that failed or errored out
+    pub fn failed(&self) -> impl Iterator<Item = &BuildJob> {
+        self.jobs.iter().filter(|job| job.failed())
+    }
+}
+
+impl IntoIterator for BuildJobs {
+    type Item = BuildJob;
+    type IntoIter = std::vec::IntoIter<BuildJob>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.jobs.into_iter()
+    }
+}
+
+impl BuildJob {
+    /// `consolidated_status` when the API set it, otherwise `status`
+    pub fn result(&self) -> &str {
+        self.consolidated_status
+            .as_deref()
+            .or(self.status.as_deref())
+            .unwrap_or("unknown")
+    }
+
+    /// Whether the job failed or errored out
+    pub fn failed(&self) -> bool {
+        matches!(self.result(), "failed" | "error" | "errored")
+    }
+
+    /// Whether the job errored out, ex. it timed out or the session broke,
+    /// rather than failing an assertion
+    pub fn errored(&self) -> bool {
+        matches!(self.result(), "error" | "errored")
+    }
+
+    /// Seconds between the start and the end of the job, if it has finished
+    pub fn duration(&self) -> Option<i64> {
+        match (self.start_time, self.end_time) {
+            (Some(start), Some(end)) if end >= start => Some(end - start),
+            _ => None,
+        }
+    }
+
+    /// Operating system, browser and browser version, ex. `Windows 10 chrome 80`
+    pub fn platform(&self) -> String {
+        [&self.os, &self.browser, &self.browser_version]
+            .iter()
+            .filter_map(|part| part.as_deref())
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    /// Link to the test page in the web app of the job's region
+    pub fn link(&self) -> String {
+        format!("{}/tests/{}", self.region.app_host(), self.id)
+    }
+}
+
 impl Build {
-    pub fn new(build_id: &str, user: users::User) -> Result<Build, Box<dyn Error>> {
-        let info = api::build_info(build_id, user)?;
-        let build: Build = serde_json::from_value(info)?;
-        return Ok(build);
+    /// Fetch the build `build_id`, searching every region when the client
+    /// has auto region on
+    pub fn new(client: &api::SauceClient, build_id: &str) -> Result<Build> {
+        let (info, region) = client.find_build(build_id)?;
+        let mut build: Build = serde_json::from_value(info.clone())
+            .map_err(|e| ShelperError::deserialize(e, &info.to_string()))?;
+        build.region = region;
+        Ok(build)
+    }
+
+    /// Fetch every job in this build, from the region the build is in
+    pub fn jobs(&self, client: &api::SauceClient) -> Result<BuildJobs> {
+        BuildJobs::in_region(client, &self.id, self.region)
+    }
+
+    /// True once the build's status is no longer running.
+    /// A status shelper doesn't know is treated as still running.
+    pub fn finished(&self) -> bool {
+        self.status
+            .parse::<BuildStatus>()
+            .is_ok_and(|status| status != BuildStatus::Running)
+    }
+
+    /// Seconds between the start and the end of the build, if it has finished
+    pub fn duration(&self) -> Option<u64> {
+        self.end_time
+            .filter(|end| *end >= self.start_time)
+            .map(|end| end - self.start_time)
+    }
+
+    /// True if any job in the build failed or errored out
+    pub fn has_failures(&self) -> bool {
+        self.jobs.failed > 0 || self.jobs.errored > 0
+    }
+
+    /// JUnit XML report of the build with one `<testcase>` per job in `jobs`.
+    /// Failed jobs get a `<failure>`, errored jobs an `<error>` and unfinished
+    /// jobs are skipped.
+    /// The classname is the job's platform, with dots swapped for underscores
+    /// so CI servers don't read the browser version as a package name.
+    pub fn junit(&self, jobs: &BuildJobs) -> String {
+        let errors = jobs.iter().filter(|job| job.errored()).count();
+        let failures = jobs.failed().count() - errors;
+        let skipped = jobs.iter().filter(|job| job.end_time.is_none()).count();
+        let total_time: i64 = jobs.iter().filter_map(|job| job.duration()).sum();
+        let timestamp = match Utc.timestamp_opt(self.start_time as i64, 0).single() {
+            Some(time) => time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            None => String::new(),
+        };
+        let name = xml_escape(self.name.as_deref().unwrap_or(&self.id));
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{}\">\n",
+            name,
+            jobs.jobs.len(),
+            failures,
+            errors,
+            total_time
+        ));
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" id=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{}\" timestamp=\"{}\">\n",
+            name,
+            xml_escape(&self.id),
+            jobs.jobs.len(),
+            failures,
+            errors,
+            skipped,
+            total_time,
+            timestamp
+        ));
+        xml.push_str("    <properties>\n");
+        xml.push_str(&format!(
+            "      <property name=\"link\" value=\"{}\"/>\n",
+            xml_escape(&self.link())
+        ));
+        xml.push_str("    </properties>\n");
+        for job in jobs.iter() {
+            xml.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\">\n",
+                xml_escape(job.name.as_deref().unwrap_or(&job.id)),
+                xml_escape(&job.platform().replace('.', "_")),
+                job.duration().unwrap_or(0)
+            ));
+            xml.push_str("      <properties>\n");
+            xml.push_str(&format!(
+                "        <property name=\"link\" value=\"{}\"/>\n",
+                xml_escape(&job.link())
+            ));
+            xml.push_str("      </properties>\n");
+            if job.errored() {
+                let message = job.error.as_deref().unwrap_or("Test errored");
+                xml.push_str(&format!(
+                    "      <error message=\"{}\" type=\"{}\">{}</error>\n",
+                    xml_escape(message),
+                    xml_escape(job.result()),
+                    xml_escape(message)
+                ));
+            } else if job.failed() {
+                let message = job.error.as_deref().unwrap_or("Test failed");
+                xml.push_str(&format!(
+                    "      <failure message=\"{}\" type=\"{}\">{}</failure>\n",
+                    xml_escape(message),
+                    xml_escape(job.result()),
+                    xml_escape(message)
+                ));
+            } else if job.end_time.is_none() {
+                xml.push_str(&format!(
+                    "      <skipped message=\"{}\"/>\n",
+                    xml_escape(job.result())
+                ));
+            }
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+
+    /// Link to the build page in the web app of the build's region
+    pub fn link(&self) -> String {
+        format!("{}/builds/vdc/{}", self.region.app_host(), self.id)
+    }
+
+    /// `pretty_print` prints the build metadata and how many of
+    /// its jobs are in each state to stdout
+    pub fn pretty_print(&self) {
+        let _ = self.render(&render::Plain, &mut io::stdout());
+    }
+}
+
+impl Render for Build {
+    fn render(&self, f: &dyn Formatter, out: &mut dyn io::Write) -> io::Result<()> {
+        f.start(out, self.name.as_deref().unwrap_or(&self.id))?;
+        if let Some(name) = &self.name {
+            f.field(out, "Build Name", name)?;
+        }
+        f.field(out, "Build id", &self.id)?;
+        f.field(out, "Owner", &self.owner)?;
+        f.status(out, "Status", &self.status)?;
+        f.field(out, "Passed", &self.passed.to_string())?;
+        let time = |t: u64| match Utc.timestamp_opt(t as i64, 0).single() {
+            Some(time) => time.to_string(),
+            None => t.to_string(),
+        };
+        f.field(out, "Started", &time(self.start_time))?;
+        if let Some(end_time) = self.end_time {
+            f.field(out, "Ended", &time(end_time))?;
+        }
+        let jobs = format!(
+            "{} finished, {} passed, {} failed, {} errored, {} completed, {} queued, {} running",
+            self.jobs.finished,
+            self.jobs.passed,
+            self.jobs.failed,
+            self.jobs.errored,
+            self.jobs.completed,
+            self.jobs.queued,
+            self.jobs.running
+        );
+        f.field(out, "Jobs", &jobs)?;
+        f.link(out, "Link", &self.link())?;
+        f.end(out)
+    }
+}
+
+/// Blocks until a build finishes, polling `api::build_info`.  The wait
+/// between polls starts at the shortest poll interval, doubles while nothing
+/// changes and drops back to the shortest as soon as the job counters move.
+pub struct BuildWatch<'a> {
+    client: &'a api::SauceClient,
+    build_id: String,
+    timeout: Duration,
+    min_interval: Duration,
+    max_interval: Duration,
+}
+
+impl<'a> BuildWatch<'a> {
+    /// Watch `build_id` for up to an hour, polling every 5 to 60 seconds
+    pub fn new(client: &'a api::SauceClient, build_id: &str) -> BuildWatch<'a> {
+        BuildWatch {
+            client,
+            build_id: build_id.to_string(),
+            timeout: Duration::from_secs(60 * 60),
+            min_interval: Duration::from_secs(5),
+            max_interval: Duration::from_secs(60),
+        }
+    }
+
+    /// Give up with `ShelperError::Timeout` after waiting this long
+    pub fn timeout(mut self, timeout: Duration) -> BuildWatch<'a> {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Shortest and longest wait between two polls
+    pub fn poll_interval(mut self, min: Duration, max: Duration) -> BuildWatch<'a> {
+        self.min_interval = min;
+        self.max_interval = max.max(min);
+        self
+    }
+
+    /// Poll until the build finishes and return its final state.
+    /// `on_change` is called with the first poll and again every time
+    /// the status or the job counters change.
+    pub fn wait(self, mut on_change: impl FnMut(&Build)) -> Result<Build> {
+        let started = Instant::now();
+        let mut interval = self.min_interval;
+        let mut last: Option<(String, [i32; 6])> = None;
+        loop {
+            let build = Build::new(self.client, &self.build_id)?;
+            let jobs = &build.jobs;
+            let seen = (
+                build.status.clone(),
+                [
+                    jobs.queued,
+                    jobs.running,
+                    jobs.passed,
+                    jobs.failed,
+                    jobs.errored,
+                    jobs.finished,
+                ],
+            );
+            if last.as_ref() != Some(&seen) {
+                on_change(&build);
+                last = Some(seen);
+                interval = self.min_interval;
+            } else {
+                interval = (interval * 2).min(self.max_interval);
+            }
+            if build.finished() {
+                return Ok(build);
+            }
+            let elapsed = started.elapsed();
+            if elapsed >= self.timeout {
+                return Err(ShelperError::Timeout {
+                    waiting_for: format!("build {}", self.build_id),
+                    elapsed,
+                });
+            }
+            thread::sleep(interval.min(self.timeout - elapsed));
+        }
+    }
+}
+
+/// The `status` of a build
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BuildStatus {
+    Success,
+    Failed,
+    Error,
+    Complete,
+    Running,
+}
+
+impl BuildStatus {
+    /// The value of `status` returned by the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BuildStatus::Success => "success",
+            BuildStatus::Failed => "failed",
+            BuildStatus::Error => "error",
+            BuildStatus::Complete => "complete",
+            BuildStatus::Running => "running",
+        }
+    }
+}
+
+impl fmt::Display for BuildStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for BuildStatus {
+    type Err = ShelperError;
+
+    /// Also takes the job status names, ex. `passed` for `success`
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "success" | "passed" => Ok(BuildStatus::Success),
+            "failed" => Ok(BuildStatus::Failed),
+            "error" | "errored" => Ok(BuildStatus::Error),
+            "complete" => Ok(BuildStatus::Complete),
+            "running" | "in progress" | "in-progress" => Ok(BuildStatus::Running),
+            _ => Err(ShelperError::InvalidInput(format!(
+                "{} is not a build status. Use success, failed, error, complete or running",
+                s
+            ))),
+        }
+    }
+}
+
+/// Builds requested per API call
+const PAGE_SIZE: u64 = 100;
+
+/// Which builds to list.  Builds come back newest first and every filter
+/// is applied as the pages come back.
+#[derive(Debug, Clone, Default)]
+pub struct BuildQuery {
+    owner: String,
+    limit: Option<u64>,
+    from: Option<i64>,
+    to: Option<i64>,
+    statuses: Vec<BuildStatus>,
+    name_prefix: Option<String>,
+    subaccounts: bool,
+}
+
+impl BuildQuery {
+    /// Every build run by `owner`, newest first
+    pub fn new(owner: &str) -> BuildQuery {
+        BuildQuery {
+            owner: owner.to_string(),
+            ..BuildQuery::default()
+        }
+    }
+
+    /// Return at most `limit` matching builds
+    pub fn limit(mut self, limit: u64) -> BuildQuery {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Builds created at or after `from`, a unix timestamp
+    pub fn from(mut self, from: i64) -> BuildQuery {
+        self.from = Some(from);
+        self
+    }
+
+    /// Builds created at or before `to`, a unix timestamp
+    pub fn to(mut self, to: i64) -> BuildQuery {
+        self.to = Some(to);
+        self
+    }
+
+    /// Builds with this status.  Can be called more than once
+    /// to match any of several statuses.
+    pub fn status(mut self, status: BuildStatus) -> BuildQuery {
+        self.statuses.push(status);
+        self
+    }
+
+    /// Builds whose name starts with `prefix`, ex. a branch name
+    pub fn name_prefix(mut self, prefix: &str) -> BuildQuery {
+        self.name_prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Include the builds of every user under the owner.  Needs an admin key.
+    pub fn subaccounts(mut self, subaccounts: bool) -> BuildQuery {
+        self.subaccounts = subaccounts;
+        self
+    }
+
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    /// True if `build` passes every filter, including the time window
+    pub fn matches(&self, build: &Build) -> bool {
+        let created = build.creation_time as i64;
+        self.from.is_none_or(|from| created >= from)
+            && self.to.is_none_or(|to| created <= to)
+            && (self.statuses.is_empty()
+                || self
+                    .statuses
+                    .iter()
+                    .any(|status| status.as_str() == build.status))
+            && self.name_prefix.as_ref().is_none_or(|prefix| {
+                build
+                    .name
+                    .as_ref()
+                    .is_some_and(|name| name.starts_with(prefix.as_str()))
+            })
+    }
+
+    /// Lazily fetch the matching builds, one page of the /builds API at a time
+    pub fn fetch<'a>(&self, client: &'a api::SauceClient) -> BuildList<'a> {
+        BuildList {
+            client,
+            query: self.clone(),
+            skip: 0,
+            yielded: 0,
+            page: VecDeque::new(),
+            last_ids: Vec::new(),
+            done: false,
+        }
+    }
+}
+
+/// `list` returns the builds matching `query`, newest first
+pub fn list(client: &api::SauceClient, query: &BuildQuery) -> Result<Vec<Build>> {
+    query.fetch(client).collect()
+}
+
+/// Walks the builds of a user page by page as the iterator is consumed.
+/// Created by `BuildQuery::fetch`.
+pub struct BuildList<'a> {
+    client: &'a api::SauceClient,
+    query: BuildQuery,
+    skip: u64,
+    yielded: u64,
+    page: VecDeque<Build>,
+    /// Ids of the previous page, to notice a server that ignores `skip`
+    last_ids: Vec<String>,
+    done: bool,
+}
+
+impl BuildList<'_> {
+    fn fetch_page(&mut self) -> Result<()> {
+        let body = self.client.user_builds_page(
+            &self.query.owner,
+            PAGE_SIZE,
+            self.skip,
+            self.query.from,
+            self.query.to,
+            self.query.subaccounts,
+        )?;
+        let builds: Vec<Build> =
+            serde_json::from_str(&body).map_err(|e| ShelperError::deserialize(e, &body))?;
+        if (builds.len() as u64) < PAGE_SIZE {
+            self.done = true;
+        }
+        let ids: Vec<String> = builds.iter().map(|build| build.id.clone()).collect();
+        if !ids.is_empty() && ids == self.last_ids {
+            // the same page again, there is no next one
+            self.done = true;
+            return Ok(());
+        }
+        self.last_ids = ids;
+        self.skip += builds.len() as u64;
+        for mut build in builds {
+            build.region = self.client.region();
+            self.page.push_back(build);
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for BuildList<'_> {
+    type Item = Result<Build>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.query.limit.is_some_and(|limit| self.yielded >= limit) {
+            return None;
+        }
+        loop {
+            if self.page.is_empty() {
+                if self.done {
+                    return None;
+                }
+                if let Err(e) = self.fetch_page() {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+            let build = self.page.pop_front()?;
+            if self
+                .query
+                .from
+                .is_some_and(|from| (build.creation_time as i64) < from)
+            {
+                // newest first, so everything after this is older too
+                self.done = true;
+                self.page.clear();
+                return None;
+            }
+            if self.query.matches(&build) {
+                self.yielded += 1;
+                return Some(Ok(build));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+use super::fixtures::{build_json, FixtureServer, OWNER};
+
+#[test]
+fn build_jobs_are_typed() {
+    let server = FixtureServer::start();
+    let client = server.client();
+    let build = Build::new(&client, "91ee45d589ce4177981bf22f911f22c5").unwrap();
+    let jobs = build.jobs(&client).unwrap();
+    assert_eq!(jobs.jobs.len(), 32);
+
+    let failed: Vec<&BuildJob> = jobs.failed().collect();
+    assert_eq!(failed.len(), 4);
+    assert_eq!(failed[0].id, "a8b4002ae667605b2fe05260116afe2e");
+    assert_eq!(failed[0].status.as_deref(), Some("complete"));
+    assert_eq!(failed[0].result(), "failed");
+    assert_eq!(failed[0].duration(), Some(25));
+    assert_eq!(failed[0].platform(), "Windows 10 chrome 80.0.3987.106.");
+    assert_eq!(
+        failed[0].link(),
+        "https://app.saucelabs.com/tests/a8b4002ae667605b2fe05260116afe2e"
+    );
+    assert_eq!(
+        failed[3].error.as_deref(),
+        Some("Test exceeded maximum duration after 1800 seconds")
+    );
+}
+
+#[test]
+fn build_region_is_where_it_was_found() {
+    let server = FixtureServer::start();
+    let id = "91ee45d589ce4177981bf22f911f22c5";
+    let path = format!("/rest/v1/builds/{}", id);
+    let found = super::fixtures::body_of(&format!("build_{}.json", id));
+    server.route("GET", &path, 404, r#"{"error": "Not found"}"#);
+    server.route("GET", &path, 200, &found);
+    let client = server.builder().auto_region(true).build().unwrap();
+    let build = Build::new(&client, id).unwrap();
+    // the home region (US) answered 404, EU had it
+    assert_eq!(build.region, users::Region::EU);
+    assert!(build.link().starts_with(&users::Region::EU.app_host()));
+
+    let jobs = build.jobs(&client).unwrap();
+    assert!(jobs.iter().all(|job| job.region == users::Region::EU));
+    assert!(jobs.jobs[0]
+        .link()
+        .starts_with(&users::Region::EU.app_host()));
+    // the jobs were asked for once, in the build's region
+    assert_eq!(server.requests().len(), 3);
+}
+
+/// `count` builds of `OWNER`, newest first and a minute apart.  Every third
+/// is on `main` and every other one succeeded.
+#[cfg(test)]
+fn build_history(count: u64) -> Vec<serde_json::Value> {
+    (0..count)
+        .map(|i| {
+            let name = match i % 3 {
+                0 => format!("main #{}", count - i),
+                _ => format!("feature-x #{}", count - i),
+            };
+            let status = if i % 2 == 0 { "success" } else { "failed" };
+            build_json(
+                &format!("build{}", i),
+                &name,
+                status,
+                1_600_000_000 - i * 60,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn build_query_pages() {
+    let server = FixtureServer::start();
+    let builds = build_history(150);
+    server.route_builds("limit=100&full=true&subaccounts=true", &builds[0..100]);
+    server.route_builds(
+        "limit=100&full=true&skip=100&subaccounts=true",
+        &builds[100..150],
+    );
+    let query = BuildQuery::new(OWNER).subaccounts(true);
+    let found = list(&server.client(), &query).unwrap();
+    assert_eq!(found.len(), 150);
+    assert_eq!(found[149].id, "build149");
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn build_query_filters_on_name_and_status() {
+    let server = FixtureServer::start();
+    server.route_builds("limit=100&full=true", &build_history(30));
+    let query = BuildQuery::new(OWNER)
+        .name_prefix("main")
+        .status(BuildStatus::Success);
+    let ids: Vec<String> = list(&server.client(), &query)
+        .unwrap()
+        .into_iter()
+        .map(|build| build.id)
+        .collect();
+    // every 6th build is both on main and successful
+    assert_eq!(
+        ids,
+        vec!["build0", "build6", "build12", "build18", "build24"]
+    );
+}
+
+#[test]
+fn build_query_stops_at_the_limit() {
+    let server = FixtureServer::start();
+    server.route_builds("limit=100&full=true", &build_history(100));
+    let recent = list(&server.client(), &BuildQuery::new(OWNER).limit(2)).unwrap();
+    assert_eq!(recent.len(), 2);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn build_query_stops_at_the_time_boundary() {
+    let server = FixtureServer::start();
+    let from = 1_600_000_000 - 10 * 60;
+    let to = 1_600_000_000 - 60;
+    // the API is asked for the window and the builds are checked too
+    server.route_builds(
+        &format!("limit=100&full=true&from={}&to={}", from, to),
+        &build_history(20),
+    );
+    let since = BuildQuery::new(OWNER).from(from).to(to);
+    assert_eq!(list(&server.client(), &since).unwrap().len(), 10);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn build_query_stops_when_a_page_repeats() {
+    let server = FixtureServer::start();
+    // a server that ignores skip sends the first page every time
+    let first = build_history(100);
+    server.route_builds("limit=100&full=true", &first);
+    server.route_builds("limit=100&full=true&skip=100", &first);
+    let found = list(&server.client(), &BuildQuery::new(OWNER)).unwrap();
+    assert_eq!(found.len(), 100);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn recorded_running_build_passes_the_status_filter() {
+    let server = FixtureServer::start();
+    server.route_builds(
+        "limit=100&full=true",
+        &[
+            build_json("running", "main #2", "running", 1_600_000_000),
+            build_json("done", "main #1", "success", 1_599_999_000),
+        ],
+    );
+    let query = BuildQuery::new(OWNER).status(BuildStatus::Running);
+    let running = list(&server.client(), &query).unwrap();
+    assert_eq!(running.len(), 1);
+    assert_eq!(running[0].end_time, None);
+}
+
+#[test]
+fn build_status_takes_job_status_names() {
+    assert_eq!(
+        "passed".parse::<BuildStatus>().unwrap(),
+        BuildStatus::Success
+    );
+    assert_eq!(
+        "In-Progress".parse::<BuildStatus>().unwrap(),
+        BuildStatus::Running
+    );
+    assert!("green".parse::<BuildStatus>().is_err());
+}
+
+#[test]
+fn junit_report_for_a_build() {
+    let server = FixtureServer::start();
+    let client = server.client();
+    let build = Build::new(&client, "91ee45d589ce4177981bf22f911f22c5").unwrap();
+    let mut jobs = build.jobs(&client).unwrap();
+    jobs.jobs[0].name = Some("login <page> & \"cart\"".to_string());
+    jobs.jobs[1].end_time = None;
+    let xml = build.junit(&jobs);
+
+    assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"generic build: grey Small Fresh Computer 6.0.4\" tests=\"32\" failures=\"3\" errors=\"1\""));
+    assert!(xml.contains("skipped=\"1\""));
+    assert!(xml.contains("timestamp=\"2020-04-10T00:05:00\""));
+    assert_eq!(xml.matches("<testcase ").count(), 32);
+    assert!(xml.contains("failures=\"3\" errors=\"1\" skipped=\"1\""));
+    assert_eq!(xml.matches("<failure ").count(), 3);
+    assert_eq!(xml.matches("<error ").count(), 1);
+    assert!(xml.contains(
+        "<testcase name=\"login &lt;page&gt; &amp; &quot;cart&quot;\" classname=\"Windows 10 chrome 80_0_3987_106_\" time=\"20\">"
+    ));
+    assert!(xml.contains(
+        "<property name=\"link\" value=\"https://app.saucelabs.com/tests/a8b4002ae667605b2fe05260116afe2e\"/>"
+    ));
+    assert!(xml.contains(
+        "<error message=\"Test exceeded maximum duration after 1800 seconds\" type=\"error\">"
+    ));
+    assert!(xml.ends_with("</testsuite>\n</testsuites>\n"));
+}
+
+#[test]
+fn running_build_has_no_end_time() {
+    let server = FixtureServer::start();
+    let client = server.client();
+    let build = Build::new(&client, "e0f5d8a6c35b4b51a2a1f1e2a4e6b1d3").unwrap();
+    assert!(!build.finished());
+    assert_eq!(build.end_time, None);
+    assert_eq!(build.duration(), None);
+
+    let mut out = vec![];
+    build.render(&render::Plain, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("Started"));
+    assert!(!out.contains("Ended"));
+}
+
+#[test]
+fn build_watch_waits_for_the_build_to_finish() {
+    let server = FixtureServer::start();
+    let id = "e0f5d8a6c35b4b51a2a1f1e2a4e6b1d3";
+    let path = format!("/rest/v1/builds/{}", id);
+    let running = super::fixtures::body_of(&format!("build_{}_running.json", id));
+    let mut failed: serde_json::Value = serde_json::from_str(&running).unwrap();
+    failed["status"] = "failed".into();
+    failed["end_time"] = 1_586_480_900.into();
+    failed["jobs"]["running"] = 0.into();
+    failed["jobs"]["errored"] = 1.into();
+    server.route("GET", &path, 200, &running);
+    server.route("GET", &path, 200, &running);
+    server.route("GET", &path, 200, &failed.to_string());
+    let client = server.client();
+
+    let mut seen = vec![];
+    let build = BuildWatch::new(&client, id)
+        .poll_interval(Duration::from_millis(1), Duration::from_millis(5))
+        .wait(|build| seen.push(build.status.clone()))
+        .unwrap();
+    assert!(build.finished());
+    assert!(build.has_failures());
+    assert_eq!(build.duration(), Some(500));
+    // the unchanged second poll is not reported
+    assert_eq!(seen, vec!["running", "failed"]);
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+fn build_watch_times_out_on_a_running_build() {
+    let server = FixtureServer::start();
+    let client = server.client();
+    match BuildWatch::new(&client, "e0f5d8a6c35b4b51a2a1f1e2a4e6b1d3")
+        .timeout(Duration::from_millis(20))
+        .poll_interval(Duration::from_millis(1), Duration::from_millis(5))
+        .wait(|_| {})
+    {
+        Err(ShelperError::Timeout { waiting_for, .. }) => {
+            assert_eq!(waiting_for, "build e0f5d8a6c35b4b51a2a1f1e2a4e6b1d3")
+        }
+        other => panic!("expected a timeout, got {:?}", other),
     }
 }
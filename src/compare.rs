@@ -0,0 +1,204 @@
+use super::api;
+use super::builds::{BuildJob, BuildJobs};
+use super::sauce_errors::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// What happened to a test between the base and the head build
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    /// Passed in the base build, failed in the head build
+    NewlyFailing,
+    /// Failed in the base build, passed in the head build
+    NewlyPassing,
+    StillFailing,
+    StillPassing,
+    /// Only in the head build
+    Added,
+    /// Only in the base build
+    Removed,
+}
+
+impl Change {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Change::NewlyFailing => "newly failing",
+            Change::NewlyPassing => "newly passing",
+            Change::StillFailing => "still failing",
+            Change::StillPassing => "still passing",
+            Change::Added => "added",
+            Change::Removed => "removed",
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// One test, the same name on the same platform, in both builds
+#[derive(Serialize, Debug, Clone)]
+pub struct JobChange {
+    pub name: String,
+    pub platform: String,
+    pub change: Change,
+    /// The job in the base build, `None` when the test was added
+    pub base: Option<BuildJob>,
+    /// The job in the head build, `None` when the test was removed
+    pub head: Option<BuildJob>,
+}
+
+/// Every test in two builds, matched by name and platform
+#[derive(Serialize, Debug, Clone)]
+pub struct BuildComparison {
+    pub base_build: String,
+    pub head_build: String,
+    pub changes: Vec<JobChange>,
+}
+
+/// Tests are matched on the job name plus os, browser and browser version
+fn key(job: &BuildJob) -> (String, String) {
+    let name = job.name.clone().unwrap_or_else(|| job.id.clone());
+    (name, job.platform())
+}
+
+impl BuildComparison {
+    /// Fetch the jobs of both builds and compare them
+    pub fn new(
+        client: &api::SauceClient,
+        base_build: &str,
+        head_build: &str,
+    ) -> Result<BuildComparison> {
+        let base = BuildJobs::new(client, base_build)?;
+        let head = BuildJobs::new(client, head_build)?;
+        Ok(BuildComparison::from_jobs(
+            base_build, &base, head_build, &head,
+        ))
+    }
+
+    /// Compare jobs that were already fetched.  When a test ran more than once
+    /// in a build, ex. a retry, the most recently created job counts.
+    pub fn from_jobs(
+        base_build: &str,
+        base: &BuildJobs,
+        head_build: &str,
+        head: &BuildJobs,
+    ) -> BuildComparison {
+        let latest = |jobs: &BuildJobs| {
+            let mut order = vec![];
+            let mut by_key: HashMap<(String, String), BuildJob> = HashMap::new();
+            for job in jobs.iter() {
+                let k = key(job);
+                match by_key.get(&k) {
+                    Some(seen) if seen.creation_time > job.creation_time => {}
+                    Some(_) => {
+                        by_key.insert(k, job.clone());
+                    }
+                    None => {
+                        order.push(k.clone());
+                        by_key.insert(k, job.clone());
+                    }
+                }
+            }
+            (order, by_key)
+        };
+        let (base_order, mut base_jobs) = latest(base);
+        let (head_order, mut head_jobs) = latest(head);
+
+        let mut changes = vec![];
+        for k in base_order {
+            let before = base_jobs.remove(&k);
+            let after = head_jobs.remove(&k);
+            let change = match (&before, &after) {
+                (Some(b), Some(a)) => match (b.failed(), a.failed()) {
+                    (false, true) => Change::NewlyFailing,
+                    (true, false) => Change::NewlyPassing,
+                    (true, true) => Change::StillFailing,
+                    (false, false) => Change::StillPassing,
+                },
+                _ => Change::Removed,
+            };
+            changes.push(JobChange {
+                name: k.0,
+                platform: k.1,
+                change,
+                base: before,
+                head: after,
+            });
+        }
+        for k in head_order {
+            if let Some(after) = head_jobs.remove(&k) {
+                changes.push(JobChange {
+                    name: k.0,
+                    platform: k.1,
+                    change: Change::Added,
+                    base: None,
+                    head: Some(after),
+                });
+            }
+        }
+        BuildComparison {
+            base_build: base_build.to_string(),
+            head_build: head_build.to_string(),
+            changes,
+        }
+    }
+
+    /// The tests that had this `change`
+    pub fn with(&self, change: Change) -> impl Iterator<Item = &JobChange> {
+        self.changes.iter().filter(move |c| c.change == change)
+    }
+
+    /// How many tests had this `change`
+    pub fn count(&self, change: Change) -> usize {
+        self.with(change).count()
+    }
+
+    /// True if any test passed in the base build and fails in the head build
+    pub fn has_regressions(&self) -> bool {
+        self.count(Change::NewlyFailing) > 0
+    }
+}
+
+#[cfg(test)]
+use super::fixtures::body_of;
+
+#[test]
+fn compare_matches_name_and_platform() {
+    let base: BuildJobs =
+        serde_json::from_str(&body_of("build_91ee45d589ce4177981bf22f911f22c5_jobs.json")).unwrap();
+    let mut head: BuildJobs =
+        serde_json::from_str(&body_of("build_91ee45d589ce4177981bf22f911f22c5_jobs.json")).unwrap();
+    // 0 breaks, 5 gets fixed, 14 keeps failing, 22 fails then passes on a retry
+    head.jobs[0].consolidated_status = Some("failed".to_string());
+    head.jobs[5].consolidated_status = Some("passed".to_string());
+    let mut retry = head.jobs[22].clone();
+    retry.consolidated_status = Some("passed".to_string());
+    retry.creation_time = retry.creation_time.map(|t| t + 60);
+    head.jobs.push(retry);
+    // 31 moves to a new browser version, so it's removed and added
+    head.jobs[31].browser_version = Some("81.0".to_string());
+
+    let report = BuildComparison::from_jobs("base", &base, "head", &head);
+    assert_eq!(report.changes.len(), 33);
+    assert!(report.has_regressions());
+    let newly_failing: Vec<&JobChange> = report.with(Change::NewlyFailing).collect();
+    assert_eq!(newly_failing.len(), 1);
+    assert_eq!(newly_failing[0].name, "login page");
+    assert_eq!(
+        newly_failing[0].platform,
+        "Windows 10 chrome 80.0.3987.106."
+    );
+    assert_eq!(report.count(Change::NewlyPassing), 2);
+    // 14 and the errored 27
+    assert_eq!(report.count(Change::StillFailing), 2);
+    assert_eq!(report.count(Change::Removed), 1);
+    let added: Vec<&JobChange> = report.with(Change::Added).collect();
+    assert_eq!(added.len(), 1);
+    assert!(added[0].platform.ends_with("81.0"));
+    assert!(added[0].base.is_none());
+}
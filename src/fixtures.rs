@@ -0,0 +1,448 @@
+//! Record/replay stand-in for the Sauce REST API so the test suite runs offline.
+//!
+//! Every `*.json` file in `tests/fixtures` is one recorded response:
+//!
+//! ```json
+//! {
+//!   "request": { "method": "GET", "path": "/rest/v1.1/jobs/<id>" },
+//!   "response": { "status": 200, "headers": {}, "body": { ... } }
+//! }
+//! ```
+//!
+//! `FixtureServer::start` serves them from `127.0.0.1` and `FixtureServer::client`
+//! hands out a `SauceClient` pointed at it.  Requests without a fixture get a 404.
+//!
+//! To record new fixtures against the real API set `SHELPER_RECORD=1` along with
+//! `SAUCE_USERNAME` and `SAUCE_ACCESS_KEY`.  Requests without a fixture are then
+//! forwarded to the host the client would have used, `SHELPER_RECORD_APP_UPSTREAM`
+//! for builds and `SHELPER_RECORD_UPSTREAM` for everything else (default to the
+//! US app and API hosts), and the response is written into `tests/fixtures`.
+//! When that fails the test gets a 502 saying why.
+use super::api;
+use super::users;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Username of the fake account that owns everything in `tests/fixtures`
+pub const OWNER: &str = "max.dobeck";
+
+const RECORD_ENV: &str = "SHELPER_RECORD";
+const UPSTREAM_ENV: &str = "SHELPER_RECORD_UPSTREAM";
+const APP_UPSTREAM_ENV: &str = "SHELPER_RECORD_APP_UPSTREAM";
+
+/// The first job recorded in `user_jobs_limit_5.json`, a passed firefox job
+/// owned by `OWNER`, moved to `id` and `creation_time`.  It ran for 23
+/// seconds.  Tests change the fields they care about.
+pub fn job_json(id: &str, creation_time: u64) -> serde_json::Value {
+    let body: serde_json::Value = serde_json::from_str(&body_of("user_jobs_limit_5.json")).unwrap();
+    let mut job = body[0].clone();
+    job["id"] = id.into();
+    job["video_url"] = format!("https://assets.saucelabs.com/jobs/{}/video.flv", id).into();
+    job["log_url"] = format!(
+        "https://assets.saucelabs.com/jobs/{}/selenium-server.log",
+        id
+    )
+    .into();
+    job["creation_time"] = creation_time.into();
+    job["start_time"] = (creation_time + 1).into();
+    job["end_time"] = (creation_time + 24).into();
+    job["modification_time"] = (creation_time + 25).into();
+    job
+}
+
+/// The build recorded in `build_91ee45d589ce4177981bf22f911f22c5.json`, or the
+/// running one when `status` is `running`, moved to `id`, `name`, `status` and
+/// `creation_time`.  Tests change the fields they care about.
+pub fn build_json(id: &str, name: &str, status: &str, creation_time: u64) -> serde_json::Value {
+    let recorded = match status {
+        "running" => "build_e0f5d8a6c35b4b51a2a1f1e2a4e6b1d3_running.json",
+        _ => "build_91ee45d589ce4177981bf22f911f22c5.json",
+    };
+    let mut build: serde_json::Value = serde_json::from_str(&body_of(recorded)).unwrap();
+    build["id"] = id.into();
+    build["name"] = name.into();
+    build["status"] = status.into();
+    build["passed"] = (status == "success").into();
+    build["creation_time"] = creation_time.into();
+    build["start_time"] = (creation_time + 2).into();
+    build["modification_time"] = (creation_time + 600).into();
+    if !build["end_time"].is_null() {
+        build["end_time"] = (creation_time + 600).into();
+    }
+    build
+}
+
+/// The response body recorded in `tests/fixtures/<name>`, for tests that
+/// serve it again under a different route
+pub fn body_of(name: &str) -> String {
+    let raw = fs::read_to_string(fixture_dir().join(name)).unwrap();
+    let fixture: serde_json::Value = serde_json::from_str(&raw).unwrap();
+    fixture["response"]["body"].to_string()
+}
+
+/// A canned response served for one method + path
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// A request the server received, kept so tests can assert on it
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Default)]
+struct State {
+    /// Responses per `METHOD path`.  The last response queued for a route is
+    /// served forever, anything before it is served once.
+    routes: HashMap<String, VecDeque<Response>>,
+    /// Routes a test has queued responses for, so the first `route` call
+    /// replaces the fixture loaded from disk instead of queueing behind it
+    overridden: HashSet<String>,
+    requests: Vec<Request>,
+}
+
+pub struct FixtureServer {
+    url: String,
+    state: Arc<Mutex<State>>,
+}
+
+fn fixture_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+}
+
+fn route_key(method: &str, path: &str) -> String {
+    format!("{} {}", method.to_uppercase(), path)
+}
+
+fn load_fixtures() -> HashMap<String, VecDeque<Response>> {
+    let mut routes = HashMap::new();
+    let entries = match fs::read_dir(fixture_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return routes,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let raw = fs::read_to_string(&path).unwrap();
+        let fixture: serde_json::Value = match serde_json::from_str(&raw) {
+            Ok(fixture) => fixture,
+            Err(e) => panic!("Bad fixture {}: {}", path.display(), e),
+        };
+        let request = &fixture["request"];
+        let response = &fixture["response"];
+        let mut headers = Vec::new();
+        if let Some(map) = response["headers"].as_object() {
+            for (key, value) in map {
+                headers.push((key.clone(), value.as_str().unwrap_or_default().to_string()));
+            }
+        }
+        let body = match &response["body"] {
+            serde_json::Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        let key = route_key(
+            request["method"].as_str().unwrap_or("GET"),
+            request["path"].as_str().unwrap(),
+        );
+        let mut queue = VecDeque::new();
+        queue.push_back(Response {
+            status: response["status"].as_u64().unwrap_or(200) as u16,
+            headers,
+            body,
+        });
+        routes.insert(key, queue);
+    }
+    routes
+}
+
+impl FixtureServer {
+    /// Start a server on a random local port serving everything in `tests/fixtures`
+    pub fn start() -> FixtureServer {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(State {
+            routes: load_fixtures(),
+            ..State::default()
+        }));
+        let server_state = Arc::clone(&state);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => handle(stream, &server_state),
+                    Err(_) => continue,
+                }
+            }
+        });
+        FixtureServer { url, state }
+    }
+
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    /// A `SauceClient` for the fixture owner pointed at this server
+    pub fn client(&self) -> api::SauceClient {
+        self.builder().build().unwrap()
+    }
+
+    /// A `SauceClientBuilder` for the fixture owner pointed at this server,
+    /// for tests that need to change more client settings
+    pub fn builder(&self) -> api::SauceClientBuilder {
+        let user = users::User::new(
+            Some(OWNER.to_string()),
+            Some("fixture-access-key".to_string()),
+            None,
+        )
+        .unwrap();
+        api::SauceClient::builder(user).base_url(&self.url)
+    }
+
+    /// Queue a response for `method path`.  The first call replaces any
+    /// fixture loaded from disk, later calls are served in order.
+    pub fn route(&self, method: &str, path: &str, status: u16, body: &str) {
+        self.route_with_headers(method, path, status, &[], body)
+    }
+
+    pub fn route_with_headers(
+        &self,
+        method: &str,
+        path: &str,
+        status: u16,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) {
+        let response = Response {
+            status,
+            headers: headers
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            body: body.to_string(),
+        };
+        let mut state = self.state.lock().unwrap();
+        let key = route_key(method, path);
+        if state.overridden.insert(key.clone()) {
+            state.routes.remove(&key);
+        }
+        state.routes.entry(key).or_default().push_back(response);
+    }
+
+    /// Queue `jobs` as the answer to `OWNER`'s /jobs API called with `query`,
+    /// ex. `limit=500&full=true&skip=500`
+    pub fn route_jobs(&self, query: &str, jobs: &[serde_json::Value]) {
+        self.route(
+            "GET",
+            &format!("/rest/v1/{}/jobs?{}", OWNER, query),
+            200,
+            &serde_json::Value::from(jobs.to_vec()).to_string(),
+        )
+    }
+
+    /// Queue `builds` as the answer to `OWNER`'s /builds API called with `query`
+    pub fn route_builds(&self, query: &str, builds: &[serde_json::Value]) {
+        self.route(
+            "GET",
+            &format!("/rest/v1/{}/builds?{}", OWNER, query),
+            200,
+            &serde_json::Value::from(builds.to_vec()).to_string(),
+        )
+    }
+
+    /// Every request received so far, oldest first
+    pub fn requests(&self) -> Vec<Request> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+fn read_request(stream: &mut TcpStream) -> Option<Request> {
+    let mut reader = BufReader::new(stream.try_clone().ok()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let length = headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+    Some(Request {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+fn lookup(state: &Arc<Mutex<State>>, request: &Request) -> Option<Response> {
+    let mut state = state.lock().unwrap();
+    state.requests.push(request.clone());
+    let exact = route_key(&request.method, &request.path);
+    let without_query = route_key(
+        &request.method,
+        request.path.split('?').next().unwrap_or_default(),
+    );
+    for key in [exact, without_query].iter() {
+        if let Some(queue) = state.routes.get_mut(key) {
+            let response = match queue.len() {
+                0 => continue,
+                1 => queue[0].clone(),
+                _ => queue.pop_front().unwrap(),
+            };
+            return Some(response);
+        }
+    }
+    None
+}
+
+fn handle(mut stream: TcpStream, state: &Arc<Mutex<State>>) {
+    let request = match read_request(&mut stream) {
+        Some(request) => request,
+        None => return,
+    };
+    let response = match lookup(state, &request) {
+        Some(response) => response,
+        None if std::env::var(RECORD_ENV).is_ok() => record(state, &request),
+        None => Response {
+            status: 404,
+            headers: Vec::new(),
+            body: format!(
+                r#"{{"message": "no fixture for {} {}"}}"#,
+                request.method, request.path
+            ),
+        },
+    };
+    let mut raw = format!(
+        "HTTP/1.1 {} FIXTURE\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (key, value) in response.headers.iter() {
+        raw.push_str(&format!("{}: {}\r\n", key, value));
+    }
+    raw.push_str("\r\n");
+    raw.push_str(&response.body);
+    let _ = stream.write_all(raw.as_bytes());
+}
+
+/// Forward `request` to the real API with the credentials from the environment
+/// and save the answer as a new fixture.  Anything going wrong on the way is
+/// answered with a 502 so the test fails with the reason.
+fn record(state: &Arc<Mutex<State>>, request: &Request) -> Response {
+    match forward(request) {
+        Ok(response) => {
+            let mut queue = VecDeque::new();
+            queue.push_back(response.clone());
+            state
+                .lock()
+                .unwrap()
+                .routes
+                .insert(route_key(&request.method, &request.path), queue);
+            response
+        }
+        Err(e) => Response {
+            status: 502,
+            headers: Vec::new(),
+            body: serde_json::json!({
+                "message": format!("recording {} {} failed: {}", request.method, request.path, e)
+            })
+            .to_string(),
+        },
+    }
+}
+
+/// The real host `path` is served from.  With a base url override the
+/// client sends everything here, but it only asks the web app host for
+/// builds, so the path tells the two apart.
+fn upstream(path: &str) -> String {
+    let on_app = path
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .split('/')
+        .any(|segment| segment == "builds");
+    match on_app {
+        true => std::env::var(APP_UPSTREAM_ENV).unwrap_or_else(|_| users::Region::US.app_host()),
+        false => std::env::var(UPSTREAM_ENV).unwrap_or_else(|_| users::Region::US.api_host()),
+    }
+}
+
+fn forward(request: &Request) -> Result<Response, String> {
+    let user = users::User::new(None, None, None).map_err(|e| e.to_string())?;
+    let method =
+        reqwest::Method::from_bytes(request.method.as_bytes()).map_err(|e| e.to_string())?;
+    let resp = reqwest::blocking::Client::new()
+        .request(
+            method,
+            &format!("{}{}", upstream(&request.path), request.path),
+        )
+        .basic_auth(&user.creds.username, Some(&user.creds.access_key))
+        .header("Content-Type", "application/json")
+        .body(request.body.clone())
+        .send()
+        .map_err(|e| e.to_string())?;
+    let status = resp.status().as_u16();
+    let body = resp.text().map_err(|e| e.to_string())?;
+    let stored_body = match serde_json::from_str::<serde_json::Value>(&body) {
+        Ok(json) => json,
+        Err(_) => serde_json::Value::String(body.clone()),
+    };
+    let fixture = serde_json::json!({
+        "request": { "method": request.method, "path": request.path },
+        "response": { "status": status, "headers": {}, "body": stored_body },
+    });
+    let name: String = format!("{}{}", request.method, request.path)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let file = fixture_dir().join(format!("{}.json", name.to_lowercase()));
+    let pretty = serde_json::to_string_pretty(&fixture).map_err(|e| e.to_string())?;
+    fs::create_dir_all(fixture_dir())
+        .and_then(|_| fs::write(&file, pretty))
+        .map_err(|e| format!("{}: {}", file.display(), e))?;
+    Ok(Response {
+        status,
+        headers: Vec::new(),
+        body,
+    })
+}
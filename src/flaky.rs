@@ -0,0 +1,215 @@
+use super::api;
+use super::jobs::{JobDetails, JobQuery};
+use super::sauce_errors::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A test that both passed and failed on the same platform
+#[derive(Serialize, Debug, Clone)]
+pub struct FlakyTest {
+    pub name: String,
+    pub platform: String,
+    /// Jobs with a pass or fail result.  Unfinished jobs and jobs that
+    /// never reported a result are left out.
+    pub runs: usize,
+    pub passes: usize,
+    pub failures: usize,
+    /// How many times the result changed from one run to the next, oldest first
+    pub flips: usize,
+    /// `flips` out of the `runs - 1` chances to flip.  1.0 alternates every run.
+    pub flip_rate: f64,
+    /// Session id of the most recent passing job
+    pub passing_job: String,
+    /// Session id of the most recent failing job
+    pub failing_job: String,
+}
+
+/// True for a passing job, false for a failed or errored one,
+/// `None` when the job has no result
+fn passed(job: &JobDetails) -> Option<bool> {
+    match job.consolidated_status.as_str() {
+        "passed" => Some(true),
+        "failed" | "error" => Some(false),
+        _ => None,
+    }
+}
+
+/// Group `jobs` by test name plus platform and return the tests that
+/// passed and failed in `min_runs` or more runs, flakiest first.  A test
+/// that went from passing to failing once and stayed broken is a regression,
+/// not flaky, so it needs two flips or a failure followed by a pass.
+/// Jobs without a name can't be grouped and are skipped.
+pub fn from_jobs<'a>(
+    jobs: impl IntoIterator<Item = &'a JobDetails>,
+    min_runs: usize,
+) -> Vec<FlakyTest> {
+    let mut order = vec![];
+    let mut by_test: HashMap<(String, String), Vec<&JobDetails>> = HashMap::new();
+    for job in jobs {
+        let name = match (&job.name, passed(job)) {
+            (Some(name), Some(_)) => name.clone(),
+            _ => continue,
+        };
+        let key = (name, job.platform());
+        by_test
+            .entry(key.clone())
+            .or_insert_with(|| {
+                order.push(key);
+                vec![]
+            })
+            .push(job);
+    }
+
+    let mut flaky = vec![];
+    for key in order {
+        let mut runs = by_test.remove(&key).unwrap_or_default();
+        runs.sort_by_key(|job| job.creation_time);
+        let results: Vec<bool> = runs.iter().filter_map(|job| passed(job)).collect();
+        let passes = results.iter().filter(|passed| **passed).count();
+        let failures = runs.len() - passes;
+        if runs.len() < min_runs || passes == 0 || failures == 0 {
+            continue;
+        }
+        let flips = results.windows(2).filter(|w| w[0] != w[1]).count();
+        let recovered = results.windows(2).any(|w| !w[0] && w[1]);
+        if flips < 2 && !recovered {
+            continue;
+        }
+        let latest = |want: bool| {
+            runs.iter()
+                .rev()
+                .find(|job| passed(job) == Some(want))
+                .map(|job| job.id.clone())
+                .unwrap_or_default()
+        };
+        flaky.push(FlakyTest {
+            name: key.0,
+            platform: key.1,
+            runs: runs.len(),
+            passes,
+            failures,
+            flips,
+            flip_rate: flips as f64 / (runs.len() - 1) as f64,
+            passing_job: latest(true),
+            failing_job: latest(false),
+        });
+    }
+    flaky.sort_by(|a, b| {
+        b.flip_rate
+            .total_cmp(&a.flip_rate)
+            .then(b.runs.cmp(&a.runs))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    flaky
+}
+
+/// Fetch the jobs matching `query`, ex. the last 7 days, and rank
+/// the flaky tests among them.  See `from_jobs`.
+pub fn from_history(
+    client: &api::SauceClient,
+    query: &JobQuery,
+    min_runs: usize,
+) -> Result<Vec<FlakyTest>> {
+    let jobs = query.fetch(client).collect::<Result<Vec<JobDetails>>>()?;
+    Ok(from_jobs(&jobs, min_runs))
+}
+
+#[cfg(test)]
+use super::fixtures::{job_json, FixtureServer, OWNER};
+
+/// A run of `name` on `browser` for every status, newest first like the
+/// /jobs API, numbered from `first`
+#[cfg(test)]
+fn runs(name: &str, browser: &str, statuses: &[&str], first: u64) -> Vec<serde_json::Value> {
+    statuses
+        .iter()
+        .enumerate()
+        .map(|(n, status)| {
+            let i = first + n as u64;
+            let mut job = job_json(&format!("job{}", i), 1_600_000_000 - i * 60);
+            job["name"] = name.into();
+            job["browser"] = browser.into();
+            job["consolidated_status"] = (*status).into();
+            job
+        })
+        .collect()
+}
+
+#[cfg(test)]
+fn details(jobs: Vec<serde_json::Value>) -> Vec<JobDetails> {
+    jobs.into_iter()
+        .map(|job| serde_json::from_value(job).unwrap())
+        .collect()
+}
+
+#[test]
+fn flaky_tests_ranked_by_flip_rate() {
+    // alternates every run
+    let mut jobs = runs(
+        "checkout",
+        "chrome",
+        &["passed", "failed", "passed", "error"],
+        0,
+    );
+    // failed twice, then recovered
+    jobs.extend(runs(
+        "search",
+        "chrome",
+        &["passed", "passed", "failed", "failed"],
+        4,
+    ));
+    let flaky = from_jobs(&details(jobs), 3);
+    let names: Vec<(&str, usize)> = flaky
+        .iter()
+        .map(|test| (test.name.as_str(), test.flips))
+        .collect();
+    assert_eq!(names, vec![("checkout", 3), ("search", 1)]);
+    assert_eq!(flaky[0].flip_rate, 1.0);
+    assert!((flaky[1].flip_rate - 1.0 / 3.0).abs() < 1e-9);
+}
+
+#[test]
+fn a_test_that_broke_and_stayed_broken_is_not_flaky() {
+    // passed twice, then failed every run since
+    let jobs = runs(
+        "login",
+        "chrome",
+        &["failed", "failed", "passed", "passed"],
+        0,
+    );
+    assert!(from_jobs(&details(jobs), 3).is_empty());
+}
+
+#[test]
+fn flaky_test_names_its_platform_and_example_jobs() {
+    let jobs = runs(
+        "checkout",
+        "chrome",
+        &["passed", "failed", "passed", "error"],
+        0,
+    );
+    let flaky = from_jobs(&details(jobs), 3);
+    assert_eq!(flaky[0].platform, "Windows 10 chrome 74.0.");
+    assert_eq!((flaky[0].passes, flaky[0].failures), (2, 2));
+    assert_eq!(flaky[0].passing_job, "job0");
+    assert_eq!(flaky[0].failing_job, "job1");
+}
+
+#[test]
+fn steady_tests_and_short_histories_are_not_flaky() {
+    // always passes
+    let mut jobs = runs("checkout", "firefox", &["passed", "passed", "complete"], 0);
+    // one fail, one pass: not enough runs
+    jobs.extend(runs("cart", "chrome", &["passed", "failed"], 3));
+    assert!(from_jobs(&details(jobs), 3).is_empty());
+}
+
+#[test]
+fn from_history_ranks_the_jobs_of_the_query() {
+    let server = FixtureServer::start();
+    let jobs = runs("checkout", "chrome", &["passed", "failed", "passed"], 0);
+    server.route_jobs("limit=500&full=true", &jobs);
+    let flaky = from_history(&server.client(), &JobQuery::new(OWNER), 3).unwrap();
+    assert_eq!(flaky.len(), 1);
+    assert_eq!(flaky[0].flips, 2);
+}
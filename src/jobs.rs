@@ -1,7 +1,13 @@
+use super::api;
+use super::render::{self, Formatter, Render};
+use super::sauce_errors::{Result, ShelperError};
 use super::users;
 use chrono::{Local, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
-use std::error::Error;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
 
 #[derive(Serialize, Deserialize, Debug)]
 /// Gets large batches of recently run jobs for a user.  Data
@@ -39,147 +45,860 @@ pub struct JobDetails {
     pub error: Option<String>,
     pub performance_enabled: Option<bool>,
     pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    #[serde(rename = "custom-data", default)]
+    pub custom_data: Option<serde_json::Value>,
     #[serde(skip)]
     pub region: users::Region,
 }
 
 impl JobDetails {
     /// Construct new `JobDetails` object which contains all the metadata
-    /// for a single job
-    pub fn new(
-        job_id: &str,
-        owner: &users::User,
-        super_admin: Option<&users::User>,
-    ) -> Result<JobDetails, Box<dyn Error>> {
-        let api_resp = match super::api::job_info(&owner, &job_id, super_admin) {
-            Ok(body) => body,
-            Err(e) => {
-                // eprintln!("Problem getting the Job via API: {}", e);
-                return Err(format!("Problem getting the Job via API: {}", e))?;
-            }
-        };
-        let job_json: serde_json::Value = serde_json::from_str(&api_resp).unwrap();
-        let mut job: JobDetails = match serde_json::from_value(job_json) {
-            Ok(job) => job,
-            Err(e) => panic!("{}\n{}", e, api_resp),
-        };
-        match owner.region {
-            users::Region::EU => job.region = users::Region::EU,
-            _ => (),
+    /// for a single job.  `region` is the datacenter the job was found in.
+    pub fn new(client: &api::SauceClient, job_id: &str) -> Result<JobDetails> {
+        let (api_resp, region) = client.find_job(job_id)?;
+        let mut job: JobDetails =
+            serde_json::from_str(&api_resp).map_err(|e| ShelperError::deserialize(e, &api_resp))?;
+        job.region = region;
+        Ok(job)
+    }
+
+    /// Seconds between the start and the end of the job, if it has finished
+    pub fn duration(&self) -> Option<i64> {
+        match (self.start_time, self.end_time) {
+            (Some(start), Some(end)) if end >= start => Some(end - start),
+            _ => None,
         }
-        return Ok(job);
+    }
+
+    /// Link to the test page in the web app of the job's region
+    pub fn link(&self) -> String {
+        format!("{}/tests/{}", self.region.app_host(), self.id)
+    }
+
+    /// Apply `update` to the job and return what changed.  Only fields that
+    /// differ from the job are sent, and nothing is sent when none do or with
+    /// `dry_run`.  Afterwards the job holds what the API returned.
+    pub fn update(
+        &mut self,
+        client: &api::SauceClient,
+        update: &JobUpdate,
+        dry_run: bool,
+    ) -> Result<Vec<FieldChange>> {
+        let changes = update.changes(self);
+        if dry_run || changes.is_empty() {
+            return Ok(changes);
+        }
+        let body: serde_json::Map<String, serde_json::Value> = changes
+            .iter()
+            .map(|c| (c.field.to_string(), c.to.clone()))
+            .collect();
+        let resp = client.update_job(&self.owner, &self.id, self.region, &body.into())?;
+        self.replace_with(&resp)?;
+        Ok(changes)
+    }
+
+    /// Stop the job if it is still running, ex. a session left behind by a
+    /// CI runner that died.  Afterwards the job holds what the API returned.
+    pub fn stop(&mut self, client: &api::SauceClient) -> Result<()> {
+        let resp = client.stop_job(&self.owner, &self.id, self.region)?;
+        self.replace_with(&resp)
+    }
+
+    /// Replace the job with the one in `resp`, keeping the region
+    fn replace_with(&mut self, resp: &str) -> Result<()> {
+        let mut job: JobDetails =
+            serde_json::from_str(resp).map_err(|e| ShelperError::deserialize(e, resp))?;
+        job.region = self.region;
+        *self = job;
+        Ok(())
+    }
+
+    /// The commands the job sent, from its log.json
+    pub fn command_log(&self, client: &api::SauceClient) -> Result<CommandLog> {
+        CommandLog::new(client, self)
+    }
+
+    /// Operating system, browser and browser version, ex. `Windows 10 chrome 80`
+    pub fn platform(&self) -> String {
+        format!("{} {} {}", self.os, self.browser, self.browser_version)
+            .trim()
+            .to_string()
     }
 
     /// `pretty_print` prints the details to stdout. An artisanal method
     /// to print the test details in the most beautiful way possible
     pub fn pretty_print(&self) {
-        match &self.name {
-            Some(name) => println!("Test Name: {}", name),
-            None => (),
+        let _ = self.render(&render::Plain, &mut io::stdout());
+    }
+}
+
+impl Render for JobDetails {
+    fn render(&self, f: &dyn Formatter, out: &mut dyn io::Write) -> io::Result<()> {
+        f.start(out, self.name.as_deref().unwrap_or(&self.id))?;
+        if let Some(name) = &self.name {
+            f.field(out, "Test Name", name)?;
         }
 
-        match &self.app {
-            Some(app) => match app.len() {
-                0 => (),
-                _ => println!("{}", app),
-            },
-            None => (),
+        if let Some(app) = &self.app {
+            if !app.is_empty() {
+                f.note(out, app)?;
+            }
         }
 
         match &self.passed {
-            Some(true) => println!("User marked as PASSED"),
-            Some(false) => println!("User marked as FAILED"),
+            Some(true) => f.note(out, "User marked as PASSED")?,
+            Some(false) => f.note(out, "User marked as FAILED")?,
             None => (),
         }
 
-        match &self.build {
-            Some(build) => println!("Build Name: {}", build),
-            None => (),
+        if let Some(build) = &self.build {
+            f.field(out, "Build Name", build)?;
         }
 
-        println!("Owner: {}", self.owner);
-        println!(
-            "Platform: {} {} {}",
-            self.os, self.browser, self.browser_version
-        );
-        match &self.error {
-            Some(err) => println!("Error: {}", err),
-            None => (),
+        f.field(out, "Owner", &self.owner)?;
+        f.field(out, "Platform", &self.platform())?;
+        if let Some(err) = &self.error {
+            f.field(out, "Error", err)?;
         }
-        println!("Session id: {}", self.id);
-        match &self.assigned_tunnel_id {
-            Some(tunnel_id) => println!("Tunnel id: {}", tunnel_id),
-            None => (),
+        f.field(out, "Session id", &self.id)?;
+        if let Some(tunnel_id) = &self.assigned_tunnel_id {
+            f.field(out, "Tunnel id", tunnel_id)?;
         }
-        match &self.selenium_version {
-            Some(ver) => println!("Selenium Version {}", ver),
-            None => (),
+        if let Some(ver) = &self.selenium_version {
+            f.field(out, "Selenium Version", ver)?;
         }
-        println!("Automation Backend: {}", self.automation_backend);
+        f.field(out, "Automation Backend", &self.automation_backend)?;
 
-        match &self.manual {
-            Some(live_test) => println!("Live Test (manual): {}", live_test),
-            None => (),
+        if let Some(live_test) = &self.manual {
+            f.field(out, "Live Test (manual)", &live_test.to_string())?;
         }
 
-        println!("Test Status: {}", self.consolidated_status);
+        f.status(out, "Test Status", &self.consolidated_status)?;
 
-        match &self.commands_not_successful {
-            0 => (),
-            _ => println!("Failed cmds: {}", self.commands_not_successful),
+        if self.commands_not_successful != 0 {
+            f.field(
+                out,
+                "Failed cmds",
+                &self.commands_not_successful.to_string(),
+            )?;
         }
 
-        match &self.pre_run_executable {
-            None => (),
-            Some(pre_run) => println!("Pre-run script: {}", pre_run),
+        if let Some(pre_run) = &self.pre_run_executable {
+            f.field(out, "Pre-run script", pre_run)?;
         }
 
-        println!("Proxied: {}", self.proxied);
+        f.field(out, "Proxied", &self.proxied.to_string())?;
 
-        match &self.performance_enabled {
-            Some(enabled) => println!("Perf: {}", enabled),
-            None => (),
-        }
-        match self.region {
-            users::Region::US => println!("Link: https://app.saucelabs.com/tests/{}", self.id),
-            users::Region::EU => println!(
-                "Link: https://app.eu-central-1.saucelabs.com/tests/{}",
-                self.id
-            ),
+        if let Some(enabled) = &self.performance_enabled {
+            f.field(out, "Perf", &enabled.to_string())?;
         }
-        match self.start_time {
-            None => (),
-            Some(start) => println!(
-                "Started: {} / Your_TZ: {}",
-                Utc.timestamp(start, 0).to_string(),
-                Local.timestamp(start, 0)
-            ),
+        f.link(out, "Link", &self.link())?;
+        if let Some(start) = self.start_time {
+            if let Some(utc) = Utc.timestamp_opt(start, 0).single() {
+                let started = format!("{} / Your_TZ: {}", utc, utc.with_timezone(&Local));
+                f.field(out, "Started", &started)?;
+            }
         }
+        f.end(out)
     }
 }
 
 impl BulkFullJobs {
-    /// create new `BulkFullJobs` object consisting of a user's
-    /// recent jobs. FULL API setting is enabled so more details are returned
-    pub fn new(
-        owner: &users::User,
-        super_admin: Option<&users::User>,
-        limit: u64,
-    ) -> Result<BulkFullJobs, Box<dyn Error>> {
-        let api = match super::api::recent_user_jobs(owner, super_admin, limit) {
-            Ok(body) => body,
-            Err(e) => panic!(
-                "Problem getting jobs from {}/jobs API: {}",
-                owner.creds.username, e
-            ),
+    /// create new `BulkFullJobs` object consisting of the `limit` most recent jobs
+    /// run by `owner`. FULL API setting is enabled so more details are returned.
+    /// Limits above 500 are fetched one page at a time.
+    pub fn new(client: &api::SauceClient, owner: &str, limit: u64) -> Result<BulkFullJobs> {
+        let jobs = JobHistory::new(client, owner)
+            .limit(limit)
+            .collect::<Result<Vec<JobDetails>>>()?;
+        Ok(BulkFullJobs { jobs })
+    }
+
+    /// Every job `owner` created at or after `since`, a unix timestamp,
+    /// however many pages that takes
+    pub fn since(client: &api::SauceClient, owner: &str, since: i64) -> Result<BulkFullJobs> {
+        let jobs = JobHistory::new(client, owner)
+            .since(since)
+            .collect::<Result<Vec<JobDetails>>>()?;
+        Ok(BulkFullJobs { jobs })
+    }
+
+    /// The jobs that haven't finished yet
+    pub fn running(self) -> impl Iterator<Item = JobDetails> {
+        self.jobs
+            .into_iter()
+            .filter(|job| matches!(job.consolidated_status.parse(), Ok(JobStatus::InProgress)))
+    }
+}
+
+/// How far back to look for jobs that are still running, in seconds.
+/// Well past the longest `maxDuration` Sauce allows plus time in the queue.
+pub const RUNNING_LOOKBACK: i64 = 24 * 60 * 60;
+
+/// Most jobs the /jobs API returns in one page
+const MAX_PAGE_SIZE: u64 = 500;
+
+/// Walks the job history of a user, newest first, fetching one page of
+/// the /jobs API at a time as the iterator is consumed.  Stops after
+/// `limit` jobs, at the `since` time boundary, or when the history runs out.
+pub struct JobHistory<'a> {
+    client: &'a api::SauceClient,
+    owner: String,
+    limit: Option<u64>,
+    since: Option<i64>,
+    until: Option<i64>,
+    filters: Vec<(String, String)>,
+    page_size: u64,
+    skip: u64,
+    yielded: u64,
+    page: VecDeque<JobDetails>,
+    /// Ids of the previous page, to notice a server that ignores `skip`
+    last_ids: Vec<String>,
+    done: bool,
+}
+
+impl<'a> JobHistory<'a> {
+    pub fn new(client: &'a api::SauceClient, owner: &str) -> JobHistory<'a> {
+        JobHistory {
+            client,
+            owner: owner.to_string(),
+            limit: None,
+            since: None,
+            until: None,
+            filters: Vec::new(),
+            page_size: MAX_PAGE_SIZE,
+            skip: 0,
+            yielded: 0,
+            page: VecDeque::new(),
+            last_ids: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Stop after `limit` jobs
+    pub fn limit(mut self, limit: u64) -> JobHistory<'a> {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Only jobs created at or after `since`, a unix timestamp
+    pub fn since(mut self, since: i64) -> JobHistory<'a> {
+        self.since = Some(since);
+        self
+    }
+
+    /// Only jobs created at or before `until`, a unix timestamp
+    pub fn until(mut self, until: i64) -> JobHistory<'a> {
+        self.until = Some(until);
+        self
+    }
+
+    /// Ask the /jobs API for only the jobs whose `name` is `value`,
+    /// ex. `filter("build", "nightly #12")`.  Not checked on the jobs that
+    /// come back.
+    pub fn filter(mut self, name: &str, value: &str) -> JobHistory<'a> {
+        self.filters.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Jobs requested per API call, at most 500
+    pub fn page_size(mut self, page_size: u64) -> JobHistory<'a> {
+        self.page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+        self
+    }
+
+    fn fetch_page(&mut self) -> Result<()> {
+        let size = match self.limit {
+            Some(limit) => self.page_size.min(limit - self.yielded),
+            None => self.page_size,
+        };
+        let body = self.client.user_jobs_page(
+            &self.owner,
+            size,
+            self.skip,
+            self.since,
+            self.until,
+            &self.filters,
+        )?;
+        let jobs: Vec<JobDetails> =
+            serde_json::from_str(&body).map_err(|e| ShelperError::deserialize(e, &body))?;
+        if (jobs.len() as u64) < size {
+            self.done = true;
+        }
+        let ids: Vec<String> = jobs.iter().map(|job| job.id.clone()).collect();
+        if !ids.is_empty() && ids == self.last_ids {
+            // the same page again, there is no next one
+            self.done = true;
+            return Ok(());
+        }
+        self.last_ids = ids;
+        self.skip += jobs.len() as u64;
+        for mut job in jobs {
+            job.region = self.client.region();
+            self.page.push_back(job);
+        }
+        Ok(())
+    }
+}
+
+impl JobHistory<'_> {
+    /// True once `limit` jobs were returned, so there may be more
+    fn at_limit(&self) -> bool {
+        self.limit.is_some_and(|limit| self.yielded >= limit)
+    }
+}
+
+impl Iterator for JobHistory<'_> {
+    type Item = Result<JobDetails>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.at_limit() {
+            return None;
+        }
+        if self.page.is_empty() {
+            if self.done {
+                return None;
+            }
+            if let Err(e) = self.fetch_page() {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+        let job = self.page.pop_front()?;
+        if self
+            .since
+            .is_some_and(|since| (job.creation_time as i64) < since)
+        {
+            // newest first, so everything after this is older too
+            self.done = true;
+            self.page.clear();
+            return None;
+        }
+        self.yielded += 1;
+        Some(Ok(job))
+    }
+}
+
+/// The `consolidated_status` of a job
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum JobStatus {
+    Passed,
+    Failed,
+    Error,
+    Complete,
+    InProgress,
+}
+
+impl JobStatus {
+    /// The value of `consolidated_status` returned by the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Passed => "passed",
+            JobStatus::Failed => "failed",
+            JobStatus::Error => "error",
+            JobStatus::Complete => "complete",
+            JobStatus::InProgress => "in progress",
+        }
+    }
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for JobStatus {
+    type Err = ShelperError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "passed" => Ok(JobStatus::Passed),
+            "failed" => Ok(JobStatus::Failed),
+            "error" | "errored" => Ok(JobStatus::Error),
+            "complete" => Ok(JobStatus::Complete),
+            "in progress" | "in-progress" | "running" => Ok(JobStatus::InProgress),
+            _ => Err(ShelperError::InvalidInput(format!(
+                "{} is not a job status. Use passed, failed, error, complete or in-progress",
+                s
+            ))),
+        }
+    }
+}
+
+/// Most jobs `JobQuery::fetch` looks through for filters the API can't
+/// apply when the query has no `from` bound
+pub const DEFAULT_SCAN_LIMIT: u64 = 2_000;
+
+/// Which of a user's jobs to fetch.  The time window, build, tunnel and
+/// automation backend are sent to the /jobs API.  Every filter is also
+/// checked on each job as pages come back.
+#[derive(Debug, Clone, Default)]
+pub struct JobQuery {
+    owner: String,
+    limit: Option<u64>,
+    scan_limit: Option<u64>,
+    from: Option<i64>,
+    to: Option<i64>,
+    statuses: Vec<JobStatus>,
+    browser: Option<String>,
+    os: Option<String>,
+    build: Option<String>,
+    tunnel_id: Option<String>,
+    automation_backend: Option<String>,
+    manual: Option<bool>,
+}
+
+impl JobQuery {
+    /// Every job run by `owner`, newest first
+    pub fn new(owner: &str) -> JobQuery {
+        JobQuery {
+            owner: owner.to_string(),
+            ..JobQuery::default()
+        }
+    }
+
+    /// Return at most `limit` matching jobs
+    pub fn limit(mut self, limit: u64) -> JobQuery {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Look through at most `scan_limit` jobs for ones matching the filters
+    /// the API can't apply.  See `fetch`.
+    pub fn scan_limit(mut self, scan_limit: u64) -> JobQuery {
+        self.scan_limit = Some(scan_limit);
+        self
+    }
+
+    /// Jobs created at or after `from`, a unix timestamp
+    pub fn from(mut self, from: i64) -> JobQuery {
+        self.from = Some(from);
+        self
+    }
+
+    /// Jobs created at or before `to`, a unix timestamp
+    pub fn to(mut self, to: i64) -> JobQuery {
+        self.to = Some(to);
+        self
+    }
+
+    /// Jobs with this `consolidated_status`.  Can be called more than once
+    /// to match any of several statuses.
+    pub fn status(mut self, status: JobStatus) -> JobQuery {
+        self.statuses.push(status);
+        self
+    }
+
+    /// Jobs run on this browser, ex. `chrome`.  Not case sensitive.
+    pub fn browser(mut self, browser: &str) -> JobQuery {
+        self.browser = Some(browser.to_string());
+        self
+    }
+
+    /// Jobs whose os contains `os`, ex. `windows` matches `Windows 10`.
+    pub fn os(mut self, os: &str) -> JobQuery {
+        self.os = Some(os.to_string());
+        self
+    }
+
+    /// Jobs in the build with this name
+    pub fn build(mut self, build: &str) -> JobQuery {
+        self.build = Some(build.to_string());
+        self
+    }
+
+    /// Jobs that ran through this tunnel
+    pub fn tunnel_id(mut self, tunnel_id: &str) -> JobQuery {
+        self.tunnel_id = Some(tunnel_id.to_string());
+        self
+    }
+
+    /// Jobs using this automation backend, ex. `webdriver` or `appium`
+    pub fn automation_backend(mut self, backend: &str) -> JobQuery {
+        self.automation_backend = Some(backend.to_string());
+        self
+    }
+
+    /// Only manual (live) jobs when true, only automated jobs when false
+    pub fn manual(mut self, manual: bool) -> JobQuery {
+        self.manual = Some(manual);
+        self
+    }
+
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    /// True if any filter can't be sent to the API
+    fn filters_client_side(&self) -> bool {
+        !self.statuses.is_empty()
+            || self.browser.is_some()
+            || self.os.is_some()
+            || self.manual.is_some()
+    }
+
+    /// The filters the /jobs API applies itself, as query parameters
+    fn api_filters(&self) -> Vec<(&'static str, &str)> {
+        let mut filters = vec![];
+        if let Some(build) = &self.build {
+            filters.push(("build", build.as_str()));
+        }
+        if let Some(tunnel_id) = &self.tunnel_id {
+            filters.push(("tunnel_id", tunnel_id.as_str()));
+        }
+        if let Some(backend) = &self.automation_backend {
+            filters.push(("automation_backend", backend.as_str()));
+        }
+        filters
+    }
+
+    /// True if `job` passes every filter, including the time window
+    pub fn matches(&self, job: &JobDetails) -> bool {
+        let created = job.creation_time as i64;
+        let eq = |want: &Option<String>, have: &str| {
+            want.as_ref()
+                .is_none_or(|want| want.eq_ignore_ascii_case(have))
+        };
+        self.from.is_none_or(|from| created >= from)
+            && self.to.is_none_or(|to| created <= to)
+            && (self.statuses.is_empty()
+                || self
+                    .statuses
+                    .iter()
+                    .any(|status| status.as_str() == job.consolidated_status))
+            && eq(&self.browser, &job.browser)
+            && self
+                .os
+                .as_ref()
+                .is_none_or(|os| job.os.to_lowercase().contains(&os.to_lowercase()))
+            && self
+                .build
+                .as_ref()
+                .is_none_or(|build| job.build.as_deref() == Some(build.as_str()))
+            && self
+                .tunnel_id
+                .as_ref()
+                .is_none_or(|tunnel| job.assigned_tunnel_id.as_deref() == Some(tunnel.as_str()))
+            && eq(&self.automation_backend, &job.automation_backend)
+            && self
+                .manual
+                .is_none_or(|manual| job.manual.unwrap_or(false) == manual)
+    }
+
+    /// Lazily fetch the matching jobs, one page of the /jobs API at a time.
+    /// Filters the API can't apply are checked on every job that comes back,
+    /// so one that rarely matches could walk the whole history.  To bound
+    /// that, at most `scan_limit` jobs are looked through, by default
+    /// `DEFAULT_SCAN_LIMIT` when there is no `from` bound and no limit
+    /// otherwise.  Stopping there before `limit` matches were found ends the
+    /// iterator with `ShelperError::ScanLimitReached`.
+    pub fn fetch<'a>(
+        &self,
+        client: &'a api::SauceClient,
+    ) -> impl Iterator<Item = Result<JobDetails>> + 'a {
+        let mut history = JobHistory::new(client, &self.owner);
+        if let Some(from) = self.from {
+            history = history.since(from);
+        }
+        if let Some(to) = self.to {
+            history = history.until(to);
+        }
+        for (name, value) in self.api_filters() {
+            history = history.filter(name, value);
+        }
+        let scan_limit = match self.from {
+            Some(_) => self.scan_limit,
+            None => Some(self.scan_limit.unwrap_or(DEFAULT_SCAN_LIMIT)),
+        };
+        let mut scan_limited = false;
+        match (self.filters_client_side(), self.limit, scan_limit) {
+            (false, Some(limit), _) => history = history.limit(limit),
+            (true, _, Some(scan_limit)) => {
+                history = history.limit(scan_limit);
+                scan_limited = true;
+            }
+            _ => (),
+        }
+        JobMatches {
+            history,
+            query: self.clone(),
+            remaining: self.limit.unwrap_or(u64::MAX),
+            scan_limited,
+            done: false,
+        }
+    }
+}
+
+/// The jobs of a `JobHistory` that match a `JobQuery`, see `JobQuery::fetch`
+struct JobMatches<'a> {
+    history: JobHistory<'a>,
+    query: JobQuery,
+    /// Matches still to return.  Errors don't count.
+    remaining: u64,
+    /// The history stops at the scan limit rather than the query's limit
+    scan_limited: bool,
+    done: bool,
+}
+
+impl Iterator for JobMatches<'_> {
+    type Item = Result<JobDetails>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.remaining == 0 {
+            return None;
+        }
+        loop {
+            match self.history.next() {
+                Some(Ok(job)) if self.query.matches(&job) => {
+                    self.remaining -= 1;
+                    return Some(Ok(job));
+                }
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+                None => {
+                    self.done = true;
+                    if self.scan_limited && self.history.at_limit() {
+                        return Some(Err(ShelperError::ScanLimitReached {
+                            scanned: self.history.yielded,
+                        }));
+                    }
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Metadata to change on a job with `JobDetails::update`.  Fields that are
+/// not set are left alone.
+#[derive(Debug, Clone, Default)]
+pub struct JobUpdate {
+    passed: Option<bool>,
+    name: Option<String>,
+    build: Option<String>,
+    tags: Option<Vec<String>>,
+    add_tags: Vec<String>,
+    custom_data: Option<serde_json::Value>,
+}
+
+/// One field a `JobUpdate` changes, with its value before and after
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    /// The field as the API names it, ex. `passed` or `custom-data`
+    pub field: &'static str,
+    pub from: serde_json::Value,
+    pub to: serde_json::Value,
+}
+
+impl JobUpdate {
+    pub fn new() -> JobUpdate {
+        JobUpdate::default()
+    }
+
+    /// Mark the job passed or failed
+    pub fn passed(mut self, passed: bool) -> JobUpdate {
+        self.passed = Some(passed);
+        self
+    }
+
+    pub fn name(mut self, name: &str) -> JobUpdate {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Move the job into the build named `build`
+    pub fn build(mut self, build: &str) -> JobUpdate {
+        self.build = Some(build.to_string());
+        self
+    }
+
+    /// Replace the tags of the job
+    pub fn tags(mut self, tags: &[&str]) -> JobUpdate {
+        self.tags = Some(tags.iter().map(|t| t.to_string()).collect());
+        self
+    }
+
+    /// Add `tag` to the tags of the job, or to the ones given to `tags`
+    pub fn add_tag(mut self, tag: &str) -> JobUpdate {
+        self.add_tags.push(tag.to_string());
+        self
+    }
+
+    /// Replace the custom data of the job, a JSON object
+    pub fn custom_data(mut self, custom_data: serde_json::Value) -> JobUpdate {
+        self.custom_data = Some(custom_data);
+        self
+    }
+
+    /// True if nothing was set
+    pub fn is_empty(&self) -> bool {
+        self.passed.is_none()
+            && self.name.is_none()
+            && self.build.is_none()
+            && self.tags.is_none()
+            && self.add_tags.is_empty()
+            && self.custom_data.is_none()
+    }
+
+    /// What this update would change on `job`.  Fields already at the new
+    /// value are left out.
+    pub fn changes(&self, job: &JobDetails) -> Vec<FieldChange> {
+        let mut changes = vec![];
+        let mut change = |field: &'static str, from: serde_json::Value, to: serde_json::Value| {
+            if from != to {
+                changes.push(FieldChange { field, from, to });
+            }
         };
-        let mut jobs: Vec<JobDetails> = serde_json::from_str(&api).unwrap();
-        if owner.region == users::Region::EU {
-            for job in jobs.iter_mut() {
-                job.region = users::Region::EU
+        if let Some(passed) = self.passed {
+            change("passed", job.passed.into(), passed.into());
+        }
+        if let Some(name) = &self.name {
+            change("name", job.name.clone().into(), name.clone().into());
+        }
+        if let Some(build) = &self.build {
+            change("build", job.build.clone().into(), build.clone().into());
+        }
+        let current = job.tags.clone().unwrap_or_default();
+        let mut tags = self.tags.clone().unwrap_or_else(|| current.clone());
+        for tag in self.add_tags.iter() {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
             }
         }
-        return Ok(BulkFullJobs { jobs: jobs });
+        change("tags", current.into(), tags.into());
+        if let Some(custom_data) = &self.custom_data {
+            change(
+                "custom-data",
+                job.custom_data.clone().unwrap_or_default(),
+                custom_data.clone(),
+            );
+        }
+        changes
+    }
+}
+
+/// One WebDriver command from a job's log.json
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Command {
+    /// HTTP method, ex. `POST`
+    pub method: String,
+    /// Path relative to the session, ex. `element/1/click`
+    pub path: String,
+    #[serde(default)]
+    pub request: serde_json::Value,
+    #[serde(default)]
+    pub result: serde_json::Value,
+    /// HTTP status of the response
+    #[serde(rename = "HTTPStatus")]
+    pub http_status: Option<u16>,
+    /// JSON Wire Protocol status, 0 is success
+    #[serde(rename = "statusCode")]
+    pub status_code: Option<i64>,
+    /// Unix timestamp with fractional seconds
+    pub start_time: Option<f64>,
+    /// Seconds the command took
+    pub duration: Option<f64>,
+    /// Seconds between the end of the previous command and the start of this one
+    pub between_commands: Option<f64>,
+    /// Seconds into the video this command starts at
+    pub in_video_timeline: Option<f64>,
+}
+
+impl Command {
+    /// True if the server answered with an error
+    pub fn failed(&self) -> bool {
+        self.http_status.is_some_and(|status| status >= 400)
+            || self.status_code.is_some_and(|status| status != 0)
+            || self.result["value"]["error"].is_string()
+    }
+
+    /// The error from the response, ex. `no such element: An element could not
+    /// be located...`
+    pub fn error(&self) -> Option<String> {
+        let value = &self.result["value"];
+        match (value["error"].as_str(), value["message"].as_str()) {
+            (Some(error), Some(message)) => Some(format!("{}: {}", error, message)),
+            (Some(error), None) => Some(error.to_string()),
+            (None, Some(message)) if self.failed() => Some(message.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Command {
+    /// `POST /element`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} /{}", self.method, self.path)
+    }
+}
+
+/// Every command a job sent, in order, as recorded in its log.json.
+/// `commands_not_successful` on `JobDetails` says that something failed,
+/// the log says what.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(transparent)]
+pub struct CommandLog {
+    pub commands: Vec<Command>,
+}
+
+impl CommandLog {
+    /// Download and parse the log.json of `job`
+    pub fn new(client: &api::SauceClient, job: &JobDetails) -> Result<CommandLog> {
+        let body = client
+            .job_asset(&job.owner, &job.id, job.region, "log.json", 0)?
+            .text()?;
+        serde_json::from_str(&body).map_err(|e| ShelperError::deserialize(e, &body))
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Command> {
+        self.commands.iter()
+    }
+
+    /// Commands the server answered with an error
+    pub fn failures(&self) -> impl Iterator<Item = &Command> {
+        self.commands.iter().filter(|c| c.failed())
+    }
+
+    /// The first command that failed, usually where the test went wrong
+    pub fn first_failure(&self) -> Option<&Command> {
+        self.failures().next()
+    }
+
+    /// The `count` commands that took the longest, slowest first
+    pub fn slowest(&self, count: usize) -> Vec<&Command> {
+        let mut by_duration: Vec<&Command> = self.commands.iter().collect();
+        by_duration.sort_by(|a, b| {
+            b.duration
+                .unwrap_or(0.0)
+                .total_cmp(&a.duration.unwrap_or(0.0))
+        });
+        by_duration.truncate(count);
+        by_duration
+    }
+
+    /// Commands sent after the test was idle for at least `seconds`.  A gap
+    /// close to the idle timeout (90 seconds by default) usually means the
+    /// test hung and the session was closed under it.
+    pub fn idle_gaps(&self, seconds: f64) -> impl Iterator<Item = &Command> {
+        self.commands
+            .iter()
+            .filter(move |c| c.between_commands.is_some_and(|gap| gap >= seconds))
+    }
+}
+
+impl IntoIterator for CommandLog {
+    type Item = Command;
+    type IntoIter = std::vec::IntoIter<Command>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.commands.into_iter()
     }
 }
 
@@ -218,18 +937,15 @@ fn json_serializes_job_details_obj() {
         "breakpointed": null,
         "browser": "android"
       }"#;
-    let job_json: serde_json::Value = serde_json::from_str(&job_text).unwrap();
+    let job_json: serde_json::Value = serde_json::from_str(job_text).unwrap();
     assert_eq!(job_json["id"], "30b9be879aa84313800c987b7aa325e8");
 }
 
 #[test]
 fn get_job_info_produces_jobdetails() {
-    let real_user_env_vars = super::users::User::new(None, None, None);
-    let job_text = match super::api::job_info(
-        &real_user_env_vars,
-        "30b9be879aa84313800c987b7aa325e8",
-        None,
-    ) {
+    let server = super::fixtures::FixtureServer::start();
+    let client = server.client();
+    let job_text = match client.job_info("30b9be879aa84313800c987b7aa325e8") {
         Ok(j) => j,
         Err(e) => panic!("{}", e),
     };
@@ -245,28 +961,26 @@ fn get_job_info_produces_jobdetails() {
 
 #[test]
 fn job_object_constructed() {
-    let real_user_env_vars = super::users::User::new(None, None, None);
+    let server = super::fixtures::FixtureServer::start();
+    let client = server.client();
 
-    let job: JobDetails = super::jobs::JobDetails::new(
-        "30b9be879aa84313800c987b7aa325e8",
-        &real_user_env_vars,
-        Some(&real_user_env_vars),
-    )
-    .unwrap();
+    let job: JobDetails =
+        super::jobs::JobDetails::new(&client, "30b9be879aa84313800c987b7aa325e8").unwrap();
     assert_eq!(job.id, "30b9be879aa84313800c987b7aa325e8");
     assert_eq!(job.name, Some("Simple Android EMUSIM Test".to_string()));
 }
 
 #[test]
 fn job_object_constructed_wo_admin() {
-    let real_user_env_vars = super::users::User::new(None, None, None);
+    let server = super::fixtures::FixtureServer::start();
+    let client = server
+        .builder()
+        .region(super::users::Region::US)
+        .build()
+        .unwrap();
 
-    let job: JobDetails = super::jobs::JobDetails::new(
-        "30b9be879aa84313800c987b7aa325e8",
-        &real_user_env_vars,
-        None,
-    )
-    .unwrap();
+    let job: JobDetails =
+        super::jobs::JobDetails::new(&client, "30b9be879aa84313800c987b7aa325e8").unwrap();
     assert_eq!(job.id, "30b9be879aa84313800c987b7aa325e8");
     assert_eq!(job.name, Some("Simple Android EMUSIM Test".to_string()));
 }
@@ -351,7 +1065,7 @@ fn json_serializes_to_bulk_full_jobs() {
           "browser": "firefox"
         }
       ]"#;
-    let job_json: serde_json::Value = serde_json::from_str(&raw_jobs).unwrap();
+    let job_json: serde_json::Value = serde_json::from_str(raw_jobs).unwrap();
     let latest_jobs: Vec<super::jobs::JobDetails> = serde_json::from_value(job_json).unwrap();
     println!("UserJobs object: {:?}", latest_jobs);
     assert_eq!(latest_jobs.len(), 2);
@@ -359,9 +1073,401 @@ fn json_serializes_to_bulk_full_jobs() {
 
 #[test]
 fn create_bulk_full_jobs_obj() {
-    let real_user = super::users::User::new(None, None, None);
-    let latest_jobs: BulkFullJobs =
-        super::jobs::BulkFullJobs::new(&real_user, Some(&real_user), 5).unwrap();
+    let server = super::fixtures::FixtureServer::start();
+    let client = server.client();
+    let owner = client.user().creds.username.clone();
+    let latest_jobs: BulkFullJobs = super::jobs::BulkFullJobs::new(&client, &owner, 5).unwrap();
     assert_eq!(latest_jobs.jobs.len(), 5);
-    assert_eq!(latest_jobs.jobs[0].owner, real_user.creds.username);
+    assert_eq!(latest_jobs.jobs[0].owner, owner);
+}
+
+#[test]
+fn bad_job_json_keeps_raw_body() {
+    let server = super::fixtures::FixtureServer::start();
+    server.route(
+        "GET",
+        "/rest/v1.1/jobs/30b9be879aa84313800c987b7aa325e8",
+        200,
+        r#"{"id": "30b9be879aa84313800c987b7aa325e8"}"#,
+    );
+    match JobDetails::new(&server.client(), "30b9be879aa84313800c987b7aa325e8") {
+        Err(ShelperError::Deserialize { body, .. }) => {
+            assert_eq!(body, r#"{"id": "30b9be879aa84313800c987b7aa325e8"}"#)
+        }
+        other => panic!("expected Deserialize, got {:?}", other),
+    }
+}
+
+#[test]
+fn link_uses_the_job_region() {
+    let server = super::fixtures::FixtureServer::start();
+    let client = server.builder().region(users::Region::EU).build().unwrap();
+    let job = JobDetails::new(&client, "30b9be879aa84313800c987b7aa325e8").unwrap();
+    assert_eq!(job.region, users::Region::EU);
+    assert_eq!(
+        job.link(),
+        "https://app.eu-central-1.saucelabs.com/tests/30b9be879aa84313800c987b7aa325e8"
+    );
+}
+
+#[cfg(test)]
+use super::fixtures::{job_json, FixtureServer, OWNER};
+
+/// `count` jobs of `OWNER`, newest first and a minute apart, named `<prefix><i>`
+#[cfg(test)]
+fn history(prefix: &str, count: u64, newest: u64) -> Vec<serde_json::Value> {
+    (0..count)
+        .map(|i| job_json(&format!("{}{}", prefix, i), newest - i * 60))
+        .collect()
+}
+
+#[test]
+fn job_history_walks_pages() {
+    let server = FixtureServer::start();
+    let jobs = history("job", 5, 1_600_000_000);
+    server.route_jobs("limit=2&full=true", &jobs[0..2]);
+    server.route_jobs("limit=2&full=true&skip=2", &jobs[2..4]);
+    server.route_jobs("limit=2&full=true&skip=4", &jobs[4..5]);
+    let client = server.client();
+    let ids: Vec<String> = JobHistory::new(&client, OWNER)
+        .page_size(2)
+        .map(|job| job.unwrap().id)
+        .collect();
+    assert_eq!(ids, vec!["job0", "job1", "job2", "job3", "job4"]);
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+fn job_history_stops_at_the_time_boundary() {
+    let server = FixtureServer::start();
+    let jobs = history("job", 4, 1_600_000_000);
+    let since = 1_600_000_000 - 90;
+    // the API returns older jobs than asked for, they are still left out
+    server.route_jobs(&format!("limit=2&full=true&from={}", since), &jobs[0..2]);
+    server.route_jobs(
+        &format!("limit=2&full=true&skip=2&from={}", since),
+        &jobs[2..4],
+    );
+    let client = server.client();
+    let recent: Vec<String> = JobHistory::new(&client, OWNER)
+        .page_size(2)
+        .since(since)
+        .map(|job| job.unwrap().id)
+        .collect();
+    assert_eq!(recent, vec!["job0", "job1"]);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn job_history_stops_when_a_page_repeats() {
+    let server = FixtureServer::start();
+    // a server that ignores skip sends the first page every time
+    let first = history("job", 2, 1_600_000_000);
+    server.route_jobs("limit=2&full=true", &first);
+    server.route_jobs("limit=2&full=true&skip=2", &first);
+    let client = server.client();
+    let ids: Vec<String> = JobHistory::new(&client, OWNER)
+        .page_size(2)
+        .map(|job| job.unwrap().id)
+        .collect();
+    assert_eq!(ids, vec!["job0", "job1"]);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn bulk_full_jobs_pages_past_500() {
+    let server = FixtureServer::start();
+    server.route_jobs("limit=500&full=true", &history("a", 500, 1_600_000_000));
+    server.route_jobs(
+        "limit=100&full=true&skip=500",
+        &history("b", 100, 1_500_000_000),
+    );
+    let bulk = BulkFullJobs::new(&server.client(), OWNER, 600).unwrap();
+    assert_eq!(bulk.jobs.len(), 600);
+    assert_eq!(bulk.jobs[599].id, "b99");
+}
+
+#[test]
+fn running_jobs_past_the_first_500() {
+    let server = FixtureServer::start();
+    let since = 1_599_960_000;
+    let mut jobs = history("j", 600, 1_600_000_000);
+    for i in [0, 250, 500] {
+        jobs[i]["consolidated_status"] = "in progress".into();
+    }
+    server.route_jobs(&format!("limit=500&full=true&from={}", since), &jobs[..500]);
+    server.route_jobs(
+        &format!("limit=500&full=true&skip=500&from={}", since),
+        &jobs[500..],
+    );
+    let recent = BulkFullJobs::since(&server.client(), OWNER, since).unwrap();
+    assert_eq!(recent.jobs.len(), 600);
+    let ids: Vec<String> = recent.running().map(|job| job.id).collect();
+    assert_eq!(ids, vec!["j0", "j250", "j500"]);
+}
+
+#[test]
+fn job_query_filters_on_status() {
+    let server = FixtureServer::start();
+    server.route(
+        "GET",
+        &format!("/rest/v1/{}/jobs?limit=500&full=true", OWNER),
+        200,
+        &super::fixtures::body_of("user_jobs_limit_5.json"),
+    );
+    let failed: Vec<JobDetails> = JobQuery::new(OWNER)
+        .status(JobStatus::Failed)
+        .status(JobStatus::Error)
+        .limit(5)
+        .fetch(&server.client())
+        .collect::<Result<Vec<JobDetails>>>()
+        .unwrap();
+    let ids: Vec<&str> = failed.iter().map(|job| job.id.as_str()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "0f37d3a8d2e84c2c8a8f1f16b2a3ce1d",
+            "6e1c24f3f7d84b60a0a3a66e7f1bfa54"
+        ]
+    );
+}
+
+#[test]
+fn job_query_filters_on_platform() {
+    let server = FixtureServer::start();
+    server.route(
+        "GET",
+        &format!("/rest/v1/{}/jobs?limit=500&full=true", OWNER),
+        200,
+        &super::fixtures::body_of("user_jobs_limit_5.json"),
+    );
+    let chrome_mac = JobQuery::new(OWNER)
+        .browser("Chrome")
+        .os("macos")
+        .limit(5)
+        .fetch(&server.client())
+        .count();
+    assert_eq!(chrome_mac, 1);
+}
+
+#[test]
+fn job_query_sends_the_limit_without_client_side_filters() {
+    let server = FixtureServer::start();
+    let newest = JobQuery::new(OWNER)
+        .limit(3)
+        .fetch(&server.client())
+        .count();
+    assert_eq!(newest, 3);
+    assert_eq!(
+        server.requests()[0].path,
+        format!("/rest/v1/{}/jobs?limit=3&full=true", OWNER)
+    );
+}
+
+#[test]
+fn job_query_stops_scanning_for_rare_matches() {
+    let server = FixtureServer::start();
+    server.route_jobs("limit=500&full=true", &history("a", 500, 1_600_000_000));
+    for skip in (500..DEFAULT_SCAN_LIMIT).step_by(500) {
+        let page = history(&format!("s{}-", skip), 500, 1_600_000_000 - skip * 60);
+        server.route_jobs(&format!("limit=500&full=true&skip={}", skip), &page);
+    }
+    let never: Vec<Result<JobDetails>> = JobQuery::new(OWNER)
+        .browser("netscape")
+        .limit(1)
+        .fetch(&server.client())
+        .collect();
+    match never.as_slice() {
+        [Err(ShelperError::ScanLimitReached { scanned })] => {
+            assert_eq!(*scanned, DEFAULT_SCAN_LIMIT)
+        }
+        other => panic!("expected ScanLimitReached, got {:?}", other),
+    }
+    assert_eq!(server.requests().len() as u64, DEFAULT_SCAN_LIMIT / 500);
+}
+
+#[test]
+fn job_query_scan_limit_can_be_narrowed() {
+    let server = FixtureServer::start();
+    server.route_jobs("limit=20&full=true", &history("a", 20, 1_600_000_000));
+    let narrower: Vec<Result<JobDetails>> = JobQuery::new(OWNER)
+        .browser("netscape")
+        .scan_limit(20)
+        .fetch(&server.client())
+        .collect();
+    assert!(matches!(
+        narrower.as_slice(),
+        [Err(ShelperError::ScanLimitReached { scanned: 20 })]
+    ));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn job_query_scan_ending_with_the_history_is_not_cut_short() {
+    let server = FixtureServer::start();
+    server.route_jobs("limit=500&full=true", &history("a", 12, 1_600_000_000));
+    let all = JobQuery::new(OWNER)
+        .browser("netscape")
+        .fetch(&server.client())
+        .collect::<Result<Vec<JobDetails>>>()
+        .unwrap();
+    assert!(all.is_empty());
+}
+
+#[test]
+fn job_query_sends_build_tunnel_and_backend_to_the_api() {
+    let server = FixtureServer::start();
+    let mut job = job_json("abc", 1_600_000_000);
+    job["build"] = "nightly #12".into();
+    job["assigned_tunnel_id"] = "tunnel-1".into();
+    server.route_jobs(
+        "limit=2&full=true&build=nightly+%2312&tunnel_id=tunnel-1&automation_backend=webdriver",
+        &[job],
+    );
+    let found = JobQuery::new(OWNER)
+        .build("nightly #12")
+        .tunnel_id("tunnel-1")
+        .automation_backend("webdriver")
+        .limit(2)
+        .fetch(&server.client())
+        .collect::<Result<Vec<JobDetails>>>()
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn job_query_matches_time_window_and_tunnel() {
+    let mut job: JobDetails = serde_json::from_value(job_json("abc", 1_600_000_000)).unwrap();
+    job.assigned_tunnel_id = Some("tunnel-1".to_string());
+    assert!(JobQuery::new("me").from(1_599_999_999).matches(&job));
+    assert!(!JobQuery::new("me").to(1_599_999_999).matches(&job));
+    assert!(JobQuery::new("me").tunnel_id("tunnel-1").matches(&job));
+    assert!(!JobQuery::new("me").manual(true).matches(&job));
+    assert!("in-progress".parse::<JobStatus>().unwrap() == JobStatus::InProgress);
+    assert!("bogus".parse::<JobStatus>().is_err());
+}
+
+#[test]
+fn command_log_finds_failures_and_gaps() {
+    let server = super::fixtures::FixtureServer::start();
+    let client = server.client();
+    let job = JobDetails::new(&client, "30b9be879aa84313800c987b7aa325e8").unwrap();
+    let log = job.command_log(&client).unwrap();
+    assert_eq!(log.commands.len(), 7);
+    assert_eq!(log.failures().count() as u32, job.commands_not_successful);
+
+    let failure = log.first_failure().unwrap();
+    assert_eq!(failure.to_string(), "POST /element");
+    assert_eq!(failure.http_status, Some(404));
+    assert_eq!(failure.request["value"], "welcome");
+    assert_eq!(
+        failure.error().unwrap(),
+        "no such element: An element could not be located on the page using the given search parameters."
+    );
+    assert_eq!(log.commands[2].error(), None);
+
+    let slowest: Vec<String> = log.slowest(2).iter().map(|c| c.to_string()).collect();
+    assert_eq!(slowest, vec!["POST /session", "POST /element"]);
+
+    let gaps: Vec<&Command> = log.idle_gaps(20.0).collect();
+    assert_eq!(gaps.len(), 1);
+    assert_eq!(gaps[0].in_video_timeline, Some(50.3));
+}
+
+#[cfg(test)]
+fn job_update() -> JobUpdate {
+    JobUpdate::new()
+        .passed(false)
+        .name("problem users")
+        .tags(&["nightly"])
+        .add_tag("retry")
+}
+
+#[test]
+fn update_dry_run_sends_nothing() {
+    let server = FixtureServer::start();
+    let mut job: JobDetails = serde_json::from_value(job_json("abc", 1)).unwrap();
+    let changes = job.update(&server.client(), &job_update(), true).unwrap();
+    assert!(server.requests().is_empty());
+    // the name is already set
+    let fields: Vec<&str> = changes.iter().map(|c| c.field).collect();
+    assert_eq!(fields, vec!["passed", "tags"]);
+    assert_eq!(job.passed, Some(true));
+}
+
+#[test]
+fn update_sends_only_what_changes() {
+    let server = FixtureServer::start();
+    let mut job: JobDetails = serde_json::from_value(job_json("abc", 1)).unwrap();
+    let path = format!("/rest/v1/{}/jobs/abc", OWNER);
+    let mut updated = job_json("abc", 1);
+    updated["passed"] = false.into();
+    updated["consolidated_status"] = "failed".into();
+    updated["tags"] = serde_json::json!(["nightly", "retry"]);
+    server.route("PUT", &path, 200, &updated.to_string());
+
+    let changes = job.update(&server.client(), &job_update(), false).unwrap();
+    assert_eq!(changes[1].to, serde_json::json!(["nightly", "retry"]));
+    let request = &server.requests()[0];
+    assert_eq!(request.method, "PUT");
+    assert_eq!(request.path, path);
+    let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({"passed": false, "tags": ["nightly", "retry"]})
+    );
+    assert_eq!(job.consolidated_status, "failed");
+}
+
+#[test]
+fn update_already_applied_sends_nothing() {
+    let server = FixtureServer::start();
+    let mut done = job_json("abc", 1);
+    done["passed"] = false.into();
+    done["tags"] = serde_json::json!(["nightly", "retry"]);
+    let mut job: JobDetails = serde_json::from_value(done).unwrap();
+    let changes = job.update(&server.client(), &job_update(), false).unwrap();
+    assert!(changes.is_empty());
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn running_keeps_jobs_in_progress() {
+    let mut running = job_json("abc", 2);
+    running["consolidated_status"] = "in progress".into();
+    running["passed"] = serde_json::Value::Null;
+    let bulk: BulkFullJobs = serde_json::from_value(serde_json::json!({
+        "jobs": [running, job_json("def", 1)]
+    }))
+    .unwrap();
+    let ids: Vec<String> = bulk.running().map(|job| job.id).collect();
+    assert_eq!(ids, vec!["abc"]);
+}
+
+#[test]
+fn stop_replaces_the_job() {
+    let server = FixtureServer::start();
+    let mut running = job_json("abc", 2);
+    running["consolidated_status"] = "in progress".into();
+    let mut stuck: JobDetails = serde_json::from_value(running).unwrap();
+    server.route(
+        "PUT",
+        &format!("/rest/v1/{}/jobs/abc/stop", OWNER),
+        200,
+        &job_json("abc", 2).to_string(),
+    );
+    stuck.stop(&server.client()).unwrap();
+    assert_eq!(stuck.consolidated_status, "passed");
+    assert_eq!(server.requests()[0].method, "PUT");
+}
+
+#[test]
+fn stop_missing_job_is_not_found() {
+    let server = FixtureServer::start();
+    let mut gone: JobDetails = serde_json::from_value(job_json("ghi", 1)).unwrap();
+    match gone.stop(&server.client()) {
+        Err(ShelperError::NotFound { .. }) => (),
+        other => panic!("expected NotFound, got {:?}", other),
+    }
 }
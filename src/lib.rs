@@ -8,19 +8,36 @@ extern crate chrono;
 extern crate reqwest;
 extern crate serde_json;
 
-/// API calls
-pub mod api;
 /// REST API wrapper for sauce labs.  Gets data about tests.
-
+pub mod api;
+/// Listing and downloading the logs, video and screenshots stored with a job
+pub mod assets;
 /// Authenticating a user or dealing with credentials for saucelabs.com REST API
 pub mod auth;
 /// API related to builds or build metadata
 pub mod builds;
+/// Comparing the jobs of two builds to find what broke or got fixed
+pub mod compare;
+/// Local stand-in for the Sauce REST API used by the tests
+#[cfg(test)]
+mod fixtures;
+/// Finding tests that flip between passing and failing
+pub mod flaky;
 /// API related to individual Jobs (test sessions)
 pub mod jobs;
+/// Rendering jobs, tunnels and builds as plain text, colored text, Markdown or HTML
+pub mod render;
+/// Retrying rate limited or failing requests
+pub mod retry;
 /// Custom error messages thrown when encountering problems accessing the Sauce REST API
 pub mod sauce_errors;
+/// Aggregate statistics over a set of jobs
+pub mod stats;
+/// Recognizing why a job failed and what to do about it
+pub mod triage;
 /// API to retrieve tunnel metadata
 pub mod tunnels;
 /// User data and Sauce REST API routes
 pub mod users;
+
+pub use sauce_errors::ShelperError;
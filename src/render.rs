@@ -0,0 +1,324 @@
+use std::io::{self, Write};
+
+/// Decides what a summary looks like in one markup.  `Render` types call
+/// `start`, then one method per line of the summary, then `end`.
+pub trait Formatter {
+    /// Before the first line.  `title` names the thing being rendered.
+    fn start(&self, _out: &mut dyn Write, _title: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// A labeled value, ex. `Owner: max.dobeck`
+    fn field(&self, out: &mut dyn Write, label: &str, value: &str) -> io::Result<()>;
+
+    /// A free standing line of text
+    fn note(&self, out: &mut dyn Write, text: &str) -> io::Result<()>;
+
+    /// A labeled status, ex. `passed` or `terminated`
+    fn status(&self, out: &mut dyn Write, label: &str, status: &str) -> io::Result<()> {
+        self.field(out, label, status)
+    }
+
+    /// A labeled URL
+    fn link(&self, out: &mut dyn Write, label: &str, url: &str) -> io::Result<()> {
+        self.field(out, label, url)
+    }
+
+    /// After the last line
+    fn end(&self, _out: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Something with a human readable summary, ex. a job or a tunnel
+pub trait Render {
+    /// Write the summary to `out` in the markup of `formatter`
+    fn render(&self, formatter: &dyn Formatter, out: &mut dyn Write) -> io::Result<()>;
+
+    /// The summary as a `String`, ex. for a chat message or a web page
+    fn render_to_string(&self, formatter: &dyn Formatter) -> String {
+        let mut buf = Vec::new();
+        // writing to a Vec can't fail
+        let _ = self.render(formatter, &mut buf);
+        String::from_utf8_lossy(&buf).into_owned()
+    }
+}
+
+/// `Label: value` lines, what the CLI prints
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Plain;
+
+impl Formatter for Plain {
+    fn field(&self, out: &mut dyn Write, label: &str, value: &str) -> io::Result<()> {
+        writeln!(out, "{}: {}", label, value)
+    }
+
+    fn note(&self, out: &mut dyn Write, text: &str) -> io::Result<()> {
+        writeln!(out, "{}", text)
+    }
+}
+
+/// `Plain` with bold labels and statuses colored with ANSI escape codes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Colored;
+
+impl Colored {
+    const BOLD: &'static str = "\x1b[1m";
+    const RED: &'static str = "\x1b[31m";
+    const GREEN: &'static str = "\x1b[32m";
+    const YELLOW: &'static str = "\x1b[33m";
+    const RESET: &'static str = "\x1b[0m";
+}
+
+impl Formatter for Colored {
+    fn field(&self, out: &mut dyn Write, label: &str, value: &str) -> io::Result<()> {
+        writeln!(
+            out,
+            "{}{}:{} {}",
+            Colored::BOLD,
+            label,
+            Colored::RESET,
+            value
+        )
+    }
+
+    fn note(&self, out: &mut dyn Write, text: &str) -> io::Result<()> {
+        writeln!(out, "{}", text)
+    }
+
+    fn status(&self, out: &mut dyn Write, label: &str, status: &str) -> io::Result<()> {
+        let color = match status.to_lowercase().as_str() {
+            "passed" | "success" => Colored::GREEN,
+            "failed" | "error" | "errored" => Colored::RED,
+            _ => Colored::YELLOW,
+        };
+        let value = format!("{}{}{}", color, status, Colored::RESET);
+        self.field(out, label, &value)
+    }
+}
+
+/// A heading and a bullet list
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Markdown;
+
+impl Markdown {
+    /// Backslash escape every character Markdown could read as markup and
+    /// keep the text on one line
+    fn escape(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            match c {
+                '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~' | '&' => {
+                    escaped.push('\\');
+                    escaped.push(c);
+                }
+                '\n' | '\r' => escaped.push(' '),
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    }
+
+    /// `text` as an inline code span, fenced with more backticks than it contains
+    fn code(text: &str) -> String {
+        let text = text.replace(['\n', '\r'], " ");
+        let longest = text
+            .split(|c| c != '`')
+            .map(|run| run.len())
+            .max()
+            .unwrap_or(0);
+        let fence = "`".repeat(longest + 1);
+        match text.starts_with('`') || text.ends_with('`') {
+            true => format!("{} {} {}", fence, text, fence),
+            false => format!("{}{}{}", fence, text, fence),
+        }
+    }
+
+    /// Percent encode the characters that would end an autolink or break a
+    /// table or list around it
+    fn url(url: &str) -> String {
+        let mut encoded = String::with_capacity(url.len());
+        for c in url.chars() {
+            match c {
+                ' ' | '<' | '>' | '`' | '|' | '[' | ']' | '\\' | '"' | '{' | '}' | '^' => {
+                    encoded.push_str(&format!("%{:02X}", c as u32))
+                }
+                c if c.is_control() => encoded.push_str(&format!("%{:02X}", c as u32)),
+                c => encoded.push(c),
+            }
+        }
+        encoded
+    }
+}
+
+impl Formatter for Markdown {
+    fn start(&self, out: &mut dyn Write, title: &str) -> io::Result<()> {
+        writeln!(out, "### {}\n", Markdown::escape(title))
+    }
+
+    fn field(&self, out: &mut dyn Write, label: &str, value: &str) -> io::Result<()> {
+        writeln!(
+            out,
+            "- **{}:** {}",
+            Markdown::escape(label),
+            Markdown::escape(value)
+        )
+    }
+
+    fn note(&self, out: &mut dyn Write, text: &str) -> io::Result<()> {
+        writeln!(out, "- {}", Markdown::escape(text))
+    }
+
+    fn status(&self, out: &mut dyn Write, label: &str, status: &str) -> io::Result<()> {
+        writeln!(
+            out,
+            "- **{}:** {}",
+            Markdown::escape(label),
+            Markdown::code(status)
+        )
+    }
+
+    fn link(&self, out: &mut dyn Write, label: &str, url: &str) -> io::Result<()> {
+        writeln!(
+            out,
+            "- **{}:** <{}>",
+            Markdown::escape(label),
+            Markdown::url(url)
+        )
+    }
+}
+
+/// A `<table>` with a heading, one row per line
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Html;
+
+impl Formatter for Html {
+    fn start(&self, out: &mut dyn Write, title: &str) -> io::Result<()> {
+        writeln!(
+            out,
+            "<div class=\"shelper\">\n<h3>{}</h3>\n<table>",
+            xml_escape(title)
+        )
+    }
+
+    fn field(&self, out: &mut dyn Write, label: &str, value: &str) -> io::Result<()> {
+        writeln!(
+            out,
+            "<tr><th>{}</th><td>{}</td></tr>",
+            xml_escape(label),
+            xml_escape(value)
+        )
+    }
+
+    fn note(&self, out: &mut dyn Write, text: &str) -> io::Result<()> {
+        writeln!(out, "<tr><td colspan=\"2\">{}</td></tr>", xml_escape(text))
+    }
+
+    fn status(&self, out: &mut dyn Write, label: &str, status: &str) -> io::Result<()> {
+        writeln!(
+            out,
+            "<tr><th>{}</th><td class=\"status-{}\">{}</td></tr>",
+            xml_escape(label),
+            xml_escape(&status.to_lowercase().replace(' ', "-")),
+            xml_escape(status)
+        )
+    }
+
+    fn link(&self, out: &mut dyn Write, label: &str, url: &str) -> io::Result<()> {
+        let url = xml_escape(url);
+        writeln!(
+            out,
+            "<tr><th>{}</th><td><a href=\"{}\">{}</a></td></tr>",
+            xml_escape(label),
+            url,
+            url
+        )
+    }
+
+    fn end(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "</table>\n</div>")
+    }
+}
+
+/// Escape text for an XML or HTML attribute or element.  Control characters
+/// XML 1.0 does not allow are dropped.
+pub(crate) fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\t' | '\r' => escaped.push(c),
+            c if (c as u32) < 0x20 => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+use super::fixtures::body_of;
+#[cfg(test)]
+use super::jobs::JobDetails;
+
+#[test]
+fn job_renders_in_every_markup() {
+    let mut job: JobDetails =
+        serde_json::from_str(&body_of("job_30b9be879aa84313800c987b7aa325e8.json")).unwrap();
+    job.error = Some("<timeout> & more".to_string());
+
+    let plain = job.render_to_string(&Plain);
+    assert!(plain.starts_with("Test Name: Simple Android EMUSIM Test\n"));
+    assert!(plain.contains("\nOwner: max.dobeck\n"));
+    assert!(plain.contains("\nTest Status: passed\n"));
+    assert!(plain
+        .contains("\nLink: https://app.saucelabs.com/tests/30b9be879aa84313800c987b7aa325e8\n"));
+
+    let colored = job.render_to_string(&Colored);
+    assert!(colored.contains("\x1b[1mTest Status:\x1b[0m \x1b[32mpassed\x1b[0m\n"));
+    let mut running = vec![];
+    Colored.status(&mut running, "Status", "running").unwrap();
+    assert_eq!(
+        String::from_utf8(running).unwrap(),
+        "\x1b[1mStatus:\x1b[0m \x1b[33mrunning\x1b[0m\n"
+    );
+
+    let markdown = job.render_to_string(&Markdown);
+    assert!(markdown.starts_with("### Simple Android EMUSIM Test\n\n- **Test Name:**"));
+    assert!(markdown.contains("- **Error:** \\<timeout\\> \\& more\n"));
+    assert!(markdown.contains("- **Test Status:** `passed`\n"));
+
+    let html = job.render_to_string(&Html);
+    assert!(
+        html.starts_with("<div class=\"shelper\">\n<h3>Simple Android EMUSIM Test</h3>\n<table>\n")
+    );
+    assert!(html.contains("<tr><th>Error</th><td>&lt;timeout&gt; &amp; more</td></tr>\n"));
+    assert!(html.contains("<td class=\"status-passed\">passed</td>"));
+    assert!(html.ends_with("</table>\n</div>\n"));
+
+    let mut out = Vec::new();
+    job.render(&Plain, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), plain);
+}
+
+#[test]
+fn markdown_escapes_metacharacters() {
+    let mut out = Vec::new();
+    let f = Markdown;
+    f.start(&mut out, "login | [cart] `v2`").unwrap();
+    f.field(&mut out, "Tags [ci]", "a|b *c*\nd").unwrap();
+    f.status(&mut out, "Status", "in `progress`").unwrap();
+    f.link(&mut out, "Link", "https://example.com/a b|c>[d]")
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "### login \\| \\[cart\\] \\`v2\\`\n\n\
+         - **Tags \\[ci\\]:** a\\|b \\*c\\* d\n\
+         - **Status:** `` in `progress` ``\n\
+         - **Link:** <https://example.com/a%20b%7Cc%3E%5Bd%5D>\n"
+    );
+}
@@ -0,0 +1,123 @@
+use reqwest::header::HeaderMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How a `SauceClient` retries requests that were rate limited (429),
+/// hit a server error (5xx), or timed out.  A connection that can't be made
+/// is retried once.  Delays grow exponentially from `base_delay` up to
+/// `max_delay` with jitter, unless the API says how long to wait with
+/// `Retry-After` or `X-Ratelimit-Reset`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt.  0 disables retrying.
+    pub max_retries: u32,
+    /// Delay before the first retry
+    pub base_delay: Duration,
+    /// Longest computed delay between two attempts
+    pub max_delay: Duration,
+    /// Total time budget for all attempts of a single request, waits included
+    pub max_elapsed: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            max_elapsed: Duration::from_secs(120),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry, fail on the first error
+    pub fn none() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// Capped exponential backoff with jitter for retry number `retry` (starting at 0).
+    /// The delay is somewhere between half and all of `base_delay * 2^retry`.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .checked_mul(2u32.saturating_pow(retry))
+            .unwrap_or(self.max_delay);
+        jitter(exp.min(self.max_delay))
+    }
+}
+
+/// Somewhere between half of `delay` and `delay`
+fn jitter(delay: Duration) -> Duration {
+    let half = delay / 2;
+    let spread = half.as_nanos() as u64 + 1;
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.subsec_nanos() as u64)
+        .unwrap_or(0);
+    half + Duration::from_nanos(seed % spread)
+}
+
+/// How long the API asked us to wait, from `Retry-After` (in seconds) or,
+/// when the rate limit is used up, `X-Ratelimit-Reset`.  The reset header is
+/// read as a unix timestamp when it is in the future, otherwise as seconds.
+pub fn server_delay(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+    };
+    if let Some(secs) = header("retry-after") {
+        return Some(Duration::from_secs(secs));
+    }
+    if header("x-ratelimit-remaining") == Some(0) {
+        if let Some(reset) = header("x-ratelimit-reset") {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|now| now.as_secs())
+                .unwrap_or(0);
+            return match reset > now {
+                true => Some(Duration::from_secs(reset - now)),
+                false if reset < 1_000_000_000 => Some(Duration::from_secs(reset)),
+                false => Some(Duration::from_secs(0)),
+            };
+        }
+    }
+    None
+}
+
+/// Status codes worth another attempt
+pub fn retryable_status(status: u16) -> bool {
+    matches!(status, 429 | 500 | 502 | 503 | 504)
+}
+
+#[test]
+fn backoff_grows_and_caps() {
+    let policy = RetryPolicy {
+        max_retries: 10,
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_secs(1),
+        max_elapsed: Duration::from_secs(60),
+    };
+    let first = policy.backoff(0);
+    assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+    let third = policy.backoff(2);
+    assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+    assert!(policy.backoff(30) <= Duration::from_secs(1));
+}
+
+#[test]
+fn reads_rate_limit_headers() {
+    let mut headers = HeaderMap::new();
+    assert_eq!(server_delay(&headers), None);
+
+    headers.insert("x-ratelimit-remaining", "0".parse().unwrap());
+    headers.insert("x-ratelimit-reset", "12".parse().unwrap());
+    assert_eq!(server_delay(&headers), Some(Duration::from_secs(12)));
+
+    headers.insert("retry-after", "3".parse().unwrap());
+    assert_eq!(server_delay(&headers), Some(Duration::from_secs(3)));
+}
@@ -1,7 +1,203 @@
+use super::users;
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+/// Result type returned by every public function in shelper
+pub type Result<T> = std::result::Result<T, ShelperError>;
+
+/// Everything that can go wrong while talking to the Sauce REST API.
+/// Library code returns one of these instead of panicking.
+#[derive(Debug)]
+pub enum ShelperError {
+    /// SAUCE_USERNAME or SAUCE_ACCESS_KEY was not set and no credentials were given
+    MissingCredentials { var: String },
+    /// 401 or 403 from the API
+    Unauthorized {
+        username: String,
+        masked_key: String,
+        url: String,
+    },
+    /// 404 from the API.  `region` is the datacenter searched for a job or
+    /// tunnel, `None` for resources not tied to one or when every one was searched.
+    NotFound {
+        url: String,
+        region: Option<users::Region>,
+    },
+    /// 404 for a job or tunnel in `region` that exists in `found_in`
+    WrongRegion {
+        region: users::Region,
+        found_in: users::Region,
+        url: String,
+    },
+    /// 429 from the API
+    RateLimited {
+        url: String,
+        retry_after: Option<u64>,
+    },
+    /// A request was retried until the `RetryPolicy` ran out.
+    /// `last` is the error from the final attempt.
+    RetriesExhausted {
+        attempts: u32,
+        elapsed: Duration,
+        last: Box<ShelperError>,
+    },
+    /// Waited `elapsed` for `waiting_for` to finish, ex. a build, and it didn't
+    Timeout {
+        waiting_for: String,
+        elapsed: Duration,
+    },
+    /// A `JobQuery` looked through `scanned` jobs for filters the API can't
+    /// apply and stopped before finding as many matches as it was asked for
+    ScanLimitReached { scanned: u64 },
+    /// Any other unsuccessful response
+    Api {
+        status: u16,
+        url: String,
+        body: String,
+    },
+    /// The response did not match the expected JSON.  `body` is the raw response.
+    Deserialize {
+        source: serde_json::Error,
+        body: String,
+    },
+    /// Could not connect, timed out, or the body could not be read
+    Network(reqwest::Error),
+    /// Bad arguments passed in by the caller
+    InvalidInput(String),
+    /// Reading or writing a local file, ex. a downloaded asset, failed
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+}
+
+impl ShelperError {
+    /// Wrap a serde error along with the body that failed to parse
+    pub fn deserialize(source: serde_json::Error, body: &str) -> ShelperError {
+        ShelperError::Deserialize {
+            source,
+            body: body.to_string(),
+        }
+    }
+
+    /// Wrap an IO error along with the file it happened on
+    pub fn io(source: std::io::Error, path: &std::path::Path) -> ShelperError {
+        ShelperError::Io {
+            path: path.display().to_string(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for ShelperError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShelperError::MissingCredentials { var } => write!(
+                f,
+                "Problem getting your credentials from the environment variable {}. Set it or pass the credentials in directly",
+                var
+            ),
+            ShelperError::Unauthorized {
+                username,
+                masked_key,
+                url,
+            } => write!(
+                f,
+                "Not authorized using user {}:{}****** {}",
+                username, masked_key, url
+            ),
+            ShelperError::NotFound { url, region } => match region {
+                Some(region) => write!(
+                    f,
+                    "404 Not Found in the {} region during req to {}, use --region auto to search every region",
+                    region, url
+                ),
+                None => write!(f, "404 Not Found during req to {}", url),
+            },
+            ShelperError::WrongRegion {
+                region,
+                found_in,
+                url,
+            } => write!(
+                f,
+                "404 Not Found in the {} region during req to {}. It is in the {} region, use --region {}",
+                region, url, found_in, found_in
+            ),
+            ShelperError::RateLimited { url, retry_after } => match retry_after {
+                Some(secs) => write!(
+                    f,
+                    "429 Too Many Requests during req to {}. Retry after {} seconds",
+                    url, secs
+                ),
+                None => write!(f, "429 Too Many Requests during req to {}", url),
+            },
+            ShelperError::RetriesExhausted {
+                attempts,
+                elapsed,
+                last,
+            } => write!(
+                f,
+                "Gave up after {} attempts over {:.1} seconds. Last error: {}",
+                attempts,
+                elapsed.as_secs_f64(),
+                last
+            ),
+            ShelperError::Timeout {
+                waiting_for,
+                elapsed,
+            } => write!(
+                f,
+                "Gave up waiting for {} after {:.0} seconds",
+                waiting_for,
+                elapsed.as_secs_f64()
+            ),
+            ShelperError::ScanLimitReached { scanned } => write!(
+                f,
+                "Stopped after looking through the newest {} jobs, there may be more matches. Narrow the search with a start time",
+                scanned
+            ),
+            ShelperError::Api { status, url, body } => write!(
+                f,
+                "{} response during req to {}.  Response: {}",
+                status, url, body
+            ),
+            ShelperError::Deserialize { source, body } => {
+                write!(f, "Unexpected response from the API: {}\n{}", source, body)
+            }
+            ShelperError::Network(e) => write!(f, "Problem reaching the Sauce API: {}", e),
+            ShelperError::InvalidInput(msg) => write!(f, "{}", msg),
+            ShelperError::Io { path, source } => write!(f, "Problem with {}: {}", path, source),
+        }
+    }
+}
+
+impl Error for ShelperError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ShelperError::Deserialize { source, .. } => Some(source),
+            ShelperError::Network(e) => Some(e),
+            ShelperError::Io { source, .. } => Some(source),
+            ShelperError::RetriesExhausted { last, .. } => Some(last.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ShelperError {
+    fn from(e: reqwest::Error) -> Self {
+        ShelperError::Network(e)
+    }
+}
+
+/// The error type from before `ShelperError`, kept so code written against
+/// it still builds
 pub mod build {
+    #![allow(deprecated)]
     use std::error::Error;
     use std::fmt;
 
+    #[deprecated(note = "the API returns `ShelperError`, match on its variants instead")]
     #[derive(Debug)]
     pub struct NoJobs {
         username: String,
@@ -21,7 +217,7 @@ pub mod build {
                 username: username.to_string(),
                 masked_key: masked_key.to_string(),
                 url: url.to_string(),
-                resp: resp,
+                resp,
             }
         }
     }
@@ -0,0 +1,201 @@
+use super::api;
+use super::jobs::{JobDetails, JobQuery};
+use super::sauce_errors::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// How many jobs in a group, and how many of them passed or failed.
+/// Errored jobs count as failed.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Tally {
+    pub jobs: usize,
+    pub passed: usize,
+    pub failed: usize,
+}
+
+impl Tally {
+    fn add(&mut self, job: &JobDetails) {
+        self.jobs += 1;
+        match job.consolidated_status.as_str() {
+            "passed" => self.passed += 1,
+            "failed" | "error" => self.failed += 1,
+            _ => {}
+        }
+    }
+}
+
+/// Job durations in seconds, for jobs that have finished
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DurationStats {
+    pub average: f64,
+    pub p50: i64,
+    pub p95: i64,
+    pub max: i64,
+}
+
+/// How often an error message came up
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ErrorCount {
+    pub error: String,
+    pub jobs: usize,
+}
+
+/// Summary of a set of jobs, ex. a week of a team's tests
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct JobStats {
+    pub total: usize,
+    /// Jobs per `consolidated_status`
+    pub by_status: BTreeMap<String, usize>,
+    /// Keyed by browser and browser version, ex. `chrome 80.0`
+    pub by_browser: BTreeMap<String, Tally>,
+    pub by_os: BTreeMap<String, Tally>,
+    pub by_automation_backend: BTreeMap<String, Tally>,
+    /// `None` when none of the jobs have finished
+    pub duration: Option<DurationStats>,
+    /// Most frequent first
+    pub errors: Vec<ErrorCount>,
+    /// Jobs that went through the Sauce proxy
+    pub proxied: usize,
+    /// Jobs that ran through a Sauce Connect tunnel
+    pub tunneled: usize,
+}
+
+impl JobStats {
+    /// Summarize `jobs`
+    pub fn from_jobs<'a>(jobs: impl IntoIterator<Item = &'a JobDetails>) -> JobStats {
+        let mut stats = JobStats::default();
+        let mut durations = vec![];
+        let mut errors: HashMap<&str, usize> = HashMap::new();
+        for job in jobs {
+            stats.total += 1;
+            *stats
+                .by_status
+                .entry(job.consolidated_status.clone())
+                .or_default() += 1;
+            stats
+                .by_browser
+                .entry(format!("{} {}", job.browser, job.browser_version))
+                .or_default()
+                .add(job);
+            stats.by_os.entry(job.os.clone()).or_default().add(job);
+            stats
+                .by_automation_backend
+                .entry(job.automation_backend.clone())
+                .or_default()
+                .add(job);
+            if let Some(duration) = job.duration() {
+                durations.push(duration);
+            }
+            if let Some(error) = job.error.as_deref().filter(|e| !e.is_empty()) {
+                *errors.entry(error).or_default() += 1;
+            }
+            if job.proxied {
+                stats.proxied += 1;
+            }
+            if job.assigned_tunnel_id.is_some() {
+                stats.tunneled += 1;
+            }
+        }
+
+        if !durations.is_empty() {
+            durations.sort_unstable();
+            // nearest rank percentile
+            let percentile = |p: usize| durations[(durations.len() * p).div_ceil(100).max(1) - 1];
+            stats.duration = Some(DurationStats {
+                average: durations.iter().sum::<i64>() as f64 / durations.len() as f64,
+                p50: percentile(50),
+                p95: percentile(95),
+                max: durations[durations.len() - 1],
+            });
+        }
+        stats.errors = errors
+            .into_iter()
+            .map(|(error, jobs)| ErrorCount {
+                error: error.to_string(),
+                jobs,
+            })
+            .collect();
+        stats
+            .errors
+            .sort_by(|a, b| b.jobs.cmp(&a.jobs).then_with(|| a.error.cmp(&b.error)));
+        stats
+    }
+
+    /// Fetch the jobs matching `query` and summarize them
+    pub fn from_history(client: &api::SauceClient, query: &JobQuery) -> Result<JobStats> {
+        let jobs = query.fetch(client).collect::<Result<Vec<JobDetails>>>()?;
+        Ok(JobStats::from_jobs(&jobs))
+    }
+
+    /// Share of the jobs that went through the Sauce proxy, 0.0 to 1.0
+    pub fn proxied_share(&self) -> f64 {
+        share(self.proxied, self.total)
+    }
+
+    /// Share of the jobs that ran through a tunnel, 0.0 to 1.0
+    pub fn tunneled_share(&self) -> f64 {
+        share(self.tunneled, self.total)
+    }
+}
+
+fn share(part: usize, total: usize) -> f64 {
+    match total {
+        0 => 0.0,
+        _ => part as f64 / total as f64,
+    }
+}
+
+#[cfg(test)]
+use super::fixtures::{body_of, job_json};
+
+#[test]
+fn stats_for_recent_jobs() {
+    let mut jobs: Vec<JobDetails> =
+        serde_json::from_str(&body_of("user_jobs_limit_5.json")).unwrap();
+    jobs[1].error = jobs[3].error.clone();
+    let stats = JobStats::from_jobs(&jobs);
+
+    assert_eq!(stats.total, 5);
+    assert_eq!(stats.by_status["passed"], 3);
+    assert_eq!(stats.by_status["failed"], 1);
+    assert_eq!(stats.by_status["error"], 1);
+    let failed: usize = stats.by_os.values().map(|tally| tally.failed).sum();
+    assert_eq!(failed, 2);
+    assert_eq!(
+        stats
+            .by_automation_backend
+            .values()
+            .map(|t| t.jobs)
+            .sum::<usize>(),
+        5
+    );
+    assert_eq!(
+        stats.errors,
+        vec![ErrorCount {
+            error: "Test did not see a new command for 90 seconds. Timing out.".to_string(),
+            jobs: 2
+        }]
+    );
+    assert_eq!(stats.tunneled, 1);
+    assert_eq!(stats.proxied, 1);
+    assert!((stats.tunneled_share() - 0.2).abs() < 1e-9);
+
+    let duration = stats.duration.unwrap();
+    assert!(duration.p50 <= duration.p95 && duration.p95 <= duration.max);
+    assert!(duration.average > 0.0);
+}
+
+#[test]
+fn percentiles_use_nearest_rank() {
+    let mut jobs = vec![];
+    for i in 1..=20 {
+        let mut job = job_json(&format!("job{}", i), 1_600_000_000);
+        job["end_time"] = (1_600_000_001 + i * 10).into();
+        jobs.push(serde_json::from_value::<JobDetails>(job).unwrap());
+    }
+    let duration = JobStats::from_jobs(&jobs).duration.unwrap();
+    assert_eq!(duration.p50, 100);
+    assert_eq!(duration.p95, 190);
+    assert_eq!(duration.max, 200);
+    assert_eq!(duration.average, 105.0);
+}
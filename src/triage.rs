@@ -0,0 +1,406 @@
+use super::api;
+use super::jobs::{Command, CommandLog, JobDetails, JobStatus};
+use super::render::{Formatter, Render};
+use super::sauce_errors::{Result, ShelperError};
+use serde::Serialize;
+use std::fmt;
+use std::io::{self, Write};
+
+/// Seconds without a new command before Sauce closes a session, unless the
+/// test sets the `idleTimeout` capability
+pub const IDLE_TIMEOUT: f64 = 90.0;
+
+const IDLE: [&str; 3] = ["did not see a new command", "idle timeout", "idletimeout"];
+const MAX_DURATION: [&str; 2] = ["maximum duration", "maxduration"];
+const TUNNEL: [&str; 1] = ["tunnel"];
+/// Browser errors for a site that can't be reached, ex. through a tunnel
+const UNREACHABLE: [&str; 5] = [
+    "err_tunnel_connection_failed",
+    "err_proxy_connection_failed",
+    "err_connection_refused",
+    "err_connection_timed_out",
+    "err_name_not_resolved",
+];
+const INFRASTRUCTURE: [&str; 3] = [
+    "infrastructure error",
+    "internal server error",
+    "failed to start the browser or device",
+];
+const SESSION: [&str; 3] = [
+    "session not created",
+    "could not start a new session",
+    "failed to create session",
+];
+const NOT_FOUND: [&str; 3] = [
+    "no such element",
+    "could not be located",
+    "unable to locate element",
+];
+
+/// The usual shapes of a failed job
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureClass {
+    IdleTimeout,
+    MaxDuration,
+    TunnelUnreachable,
+    ElementNotFound,
+    SessionCreation,
+    /// The test marked the job failed and nothing else went wrong
+    MarkedFailed,
+    Infrastructure,
+    /// Failed, but not in a way shelper recognizes
+    Unknown,
+}
+
+impl FailureClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureClass::IdleTimeout => "idle timeout",
+            FailureClass::MaxDuration => "exceeded max duration",
+            FailureClass::TunnelUnreachable => "tunnel not reachable",
+            FailureClass::ElementNotFound => "element not found",
+            FailureClass::SessionCreation => "session creation failure",
+            FailureClass::MarkedFailed => "marked failed",
+            FailureClass::Infrastructure => "infrastructure error",
+            FailureClass::Unknown => "unknown",
+        }
+    }
+
+    /// What this kind of failure usually means
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            FailureClass::IdleTimeout => "Sauce closed the session because the test stopped sending commands. The test most likely hung, crashed or forgot to quit the driver.",
+            FailureClass::MaxDuration => "The job ran longer than the maximum duration and was stopped. Either the test is too long or it got stuck in a loop.",
+            FailureClass::TunnelUnreachable => "The browser could not reach the site under test. The Sauce Connect tunnel was down, not used, or could not reach the site itself.",
+            FailureClass::ElementNotFound => "The test looked for an element that was not on the page. Usually the page changed, had not finished loading, or an earlier step went wrong.",
+            FailureClass::SessionCreation => "The session never started. The capabilities asked for a platform, browser or app that could not be provided.",
+            FailureClass::MarkedFailed => "Every command succeeded but the test reported itself as failed. The failure is in an assertion of the test, not in Sauce.",
+            FailureClass::Infrastructure => "Something went wrong on the Sauce side while starting or running the VM or device.",
+            FailureClass::Unknown => "The job failed in a way that does not match a known shape.",
+        }
+    }
+
+    /// What to check or try next
+    pub fn next_steps(&self) -> &'static [&'static str] {
+        match self {
+            FailureClass::IdleTimeout => &[
+                "Look at the last commands before the gap, the test likely waited on something that never happened",
+                "Make sure the test calls driver.quit() in a finally or teardown block",
+                "Raise the idleTimeout capability if long pauses are expected",
+            ],
+            FailureClass::MaxDuration => &[
+                "Split the test into shorter tests",
+                "Raise the maxDuration capability if the test is meant to run that long",
+            ],
+            FailureClass::TunnelUnreachable => &[
+                "Check the tunnel was running for the whole job with shelper -t <tunnel-id>",
+                "Make sure the job set the tunnelIdentifier capability of the right tunnel",
+                "Confirm the site can be reached from the machine running Sauce Connect",
+            ],
+            FailureClass::ElementNotFound => &[
+                "Watch the video at the failing command to see what the page looked like",
+                "Wait for the element explicitly instead of relying on timing",
+                "Check the locator still matches the current version of the page",
+            ],
+            FailureClass::SessionCreation => &[
+                "Compare the requested capabilities with the platform configurator",
+                "For app tests, check the app was uploaded to Sauce storage and the name matches",
+            ],
+            FailureClass::MarkedFailed => &[
+                "Look at the test's own output for the failing assertion",
+                "Use the video and screenshots to see the state of the page when it failed",
+            ],
+            FailureClass::Infrastructure => &[
+                "Rerun the job, these are usually transient",
+                "If it keeps happening on the same platform, escalate with the job id to Sauce support",
+            ],
+            FailureClass::Unknown => &[
+                "Read the error and the first failing command",
+                "Download the logs with shelper job <id> --download-assets <dir>",
+            ],
+        }
+    }
+}
+
+impl fmt::Display for FailureClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Why a job failed, with the evidence it is based on
+#[derive(Serialize, Debug, Clone)]
+pub struct Diagnosis {
+    pub job_id: String,
+    pub status: String,
+    /// `None` when the job passed or hasn't finished
+    pub class: Option<FailureClass>,
+    pub explanation: Option<String>,
+    pub next_steps: Vec<String>,
+    /// The `error` of the job
+    pub error: Option<String>,
+    pub failing_command: Option<Command>,
+    /// What the class is based on, one finding per line
+    pub evidence: Vec<String>,
+}
+
+impl Diagnosis {
+    /// Diagnose `job` using its log.json.  A job without a log, ex. one that
+    /// never started, is diagnosed from its details alone.
+    pub fn new(client: &api::SauceClient, job: &JobDetails) -> Result<Diagnosis> {
+        let log = match job.command_log(client) {
+            Ok(log) => Some(log),
+            Err(ShelperError::NotFound { .. }) | Err(ShelperError::WrongRegion { .. }) => None,
+            Err(e) => return Err(e),
+        };
+        Ok(Diagnosis::from_job(job, log.as_ref()))
+    }
+
+    /// Diagnose a job whose log, if any, was already fetched
+    pub fn from_job(job: &JobDetails, log: Option<&CommandLog>) -> Diagnosis {
+        let mut diagnosis = Diagnosis {
+            job_id: job.id.clone(),
+            status: job.consolidated_status.clone(),
+            class: None,
+            explanation: None,
+            next_steps: vec![],
+            error: job.error.clone(),
+            failing_command: log.and_then(|l| l.first_failure()).cloned(),
+            evidence: vec![],
+        };
+        let failed = match job.consolidated_status.parse::<JobStatus>() {
+            Ok(JobStatus::Passed) | Ok(JobStatus::InProgress) => false,
+            Ok(JobStatus::Complete) => job.error.is_some() || job.passed == Some(false),
+            _ => true,
+        };
+        if !failed {
+            return diagnosis;
+        }
+        let class = diagnosis.classify(job, log);
+        diagnosis.class = Some(class);
+        diagnosis.explanation = Some(class.explanation().to_string());
+        diagnosis.next_steps = class.next_steps().iter().map(|s| s.to_string()).collect();
+        diagnosis
+    }
+
+    /// Pick the class, most specific first, noting the evidence on the way
+    fn classify(&mut self, job: &JobDetails, log: Option<&CommandLog>) -> FailureClass {
+        let error = job.error.clone().unwrap_or_default().to_lowercase();
+        let failure = self.failing_command.as_ref();
+        let failure_error = failure
+            .and_then(|c| c.error())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        match log {
+            Some(log) => self.evidence.push(format!(
+                "{} of {} commands failed",
+                log.failures().count(),
+                log.commands.len()
+            )),
+            None => self
+                .evidence
+                .push("No log.json, the session may never have started".to_string()),
+        }
+        if let Some(command) = failure {
+            self.evidence.push(format!(
+                "First failing command: {} ({})",
+                command,
+                command
+                    .error()
+                    .unwrap_or_else(|| format!("HTTP {}", command.http_status.unwrap_or(0)))
+            ));
+        }
+        let idle = log.and_then(|l| idle_at_end(job, l));
+        if let Some((command, gap)) = idle {
+            self.evidence.push(format!(
+                "{:.0} seconds without a command after {} until the job ended",
+                gap, command
+            ));
+        }
+        if job.passed == Some(false) {
+            self.evidence
+                .push("The test marked the job failed".to_string());
+        }
+        let started = log
+            .and_then(|l| l.commands.first())
+            .is_some_and(|c| c.method == "POST" && c.path == "session" && !c.failed());
+
+        // the error text decides first.  A gap in the middle of the log is
+        // not an idle timeout, the command after it shows the session was alive
+        if mentions(&error, &IDLE) {
+            FailureClass::IdleTimeout
+        } else if mentions(&error, &MAX_DURATION) {
+            FailureClass::MaxDuration
+        } else if mentions(&error, &TUNNEL)
+            || (job.assigned_tunnel_id.is_some() && mentions(&failure_error, &UNREACHABLE))
+        {
+            FailureClass::TunnelUnreachable
+        } else if mentions(&error, &INFRASTRUCTURE) {
+            FailureClass::Infrastructure
+        } else if mentions(&error, &SESSION) || (!started && !error.is_empty()) {
+            FailureClass::SessionCreation
+        } else if mentions(&failure_error, &NOT_FOUND)
+            || failure.is_some_and(|c| c.status_code == Some(7))
+        {
+            FailureClass::ElementNotFound
+        } else if idle.is_some() {
+            FailureClass::IdleTimeout
+        } else if job.passed == Some(false) && failure.is_none() {
+            FailureClass::MarkedFailed
+        } else {
+            FailureClass::Unknown
+        }
+    }
+}
+
+/// The last command of the log and the seconds from its end to the end of
+/// the job, if that is longer than `IDLE_TIMEOUT`
+fn idle_at_end<'a>(job: &JobDetails, log: &'a CommandLog) -> Option<(&'a Command, f64)> {
+    let last = log.commands.last()?;
+    let last_end = last.start_time? + last.duration.unwrap_or(0.0);
+    let gap = job.end_time? as f64 - last_end;
+    match gap >= IDLE_TIMEOUT {
+        true => Some((last, gap)),
+        false => None,
+    }
+}
+
+fn mentions(text: &str, needles: &[&str]) -> bool {
+    needles.iter().any(|needle| text.contains(needle))
+}
+
+impl Render for Diagnosis {
+    fn render(&self, f: &dyn Formatter, out: &mut dyn Write) -> io::Result<()> {
+        f.start(out, &format!("Triage of job {}", self.job_id))?;
+        f.field(out, "Job id", &self.job_id)?;
+        f.status(out, "Status", &self.status)?;
+        let class = match self.class {
+            Some(class) => class,
+            None => {
+                f.note(out, "The job did not fail")?;
+                return f.end(out);
+            }
+        };
+        f.field(out, "Failure", class.as_str())?;
+        f.note(out, class.explanation())?;
+        if let Some(error) = &self.error {
+            f.field(out, "Error", error)?;
+        }
+        for finding in self.evidence.iter() {
+            f.field(out, "Evidence", finding)?;
+        }
+        for step in self.next_steps.iter() {
+            f.field(out, "Next step", step)?;
+        }
+        f.end(out)
+    }
+}
+
+#[cfg(test)]
+use super::fixtures::body_of;
+
+#[cfg(test)]
+fn fixture_job() -> (JobDetails, CommandLog) {
+    let mut job: JobDetails =
+        serde_json::from_str(&body_of("job_30b9be879aa84313800c987b7aa325e8.json")).unwrap();
+    let log: CommandLog =
+        serde_json::from_str(&body_of("job_30b9be879aa84313800c987b7aa325e8_log.json")).unwrap();
+    job.consolidated_status = "failed".to_string();
+    job.passed = Some(false);
+    (job, log)
+}
+
+#[test]
+fn triage_skips_jobs_that_did_not_fail() {
+    let job: JobDetails =
+        serde_json::from_str(&body_of("job_30b9be879aa84313800c987b7aa325e8.json")).unwrap();
+    let log: CommandLog =
+        serde_json::from_str(&body_of("job_30b9be879aa84313800c987b7aa325e8_log.json")).unwrap();
+    let diagnosis = Diagnosis::from_job(&job, Some(&log));
+    assert_eq!(diagnosis.class, None);
+    assert!(diagnosis.next_steps.is_empty());
+}
+
+#[test]
+fn triage_element_not_found() {
+    let (job, log) = fixture_job();
+    let diagnosis = Diagnosis::from_job(&job, Some(&log));
+    assert_eq!(diagnosis.class, Some(FailureClass::ElementNotFound));
+    assert_eq!(diagnosis.failing_command.unwrap().path, "element");
+    assert_eq!(diagnosis.evidence[0], "1 of 7 commands failed");
+    assert_eq!(diagnosis.next_steps.len(), 3);
+}
+
+#[test]
+fn triage_ignores_a_gap_in_the_middle_of_the_log() {
+    let (mut job, mut log) = fixture_job();
+    log.commands[6].between_commands = Some(95.0);
+    let diagnosis = Diagnosis::from_job(&job, Some(&log));
+    assert_eq!(diagnosis.class, Some(FailureClass::ElementNotFound));
+
+    job.consolidated_status = "error".to_string();
+    job.error = Some("Test exceeded maximum duration after 1800 seconds".to_string());
+    let diagnosis = Diagnosis::from_job(&job, Some(&log));
+    assert_eq!(diagnosis.class, Some(FailureClass::MaxDuration));
+}
+
+#[test]
+fn triage_idle_timeout_after_the_last_command() {
+    let (mut job, mut log) = fixture_job();
+    // the test hung after the screenshot and never deleted the session
+    log.commands.truncate(5);
+    log.commands.remove(3);
+    job.end_time = Some(1585759363 + 95);
+    let diagnosis = Diagnosis::from_job(&job, Some(&log));
+    assert_eq!(diagnosis.class, Some(FailureClass::IdleTimeout));
+    assert!(diagnosis.evidence.contains(
+        &"95 seconds without a command after GET /screenshot until the job ended".to_string()
+    ));
+
+    job.end_time = Some(1585759363 + 30);
+    let diagnosis = Diagnosis::from_job(&job, Some(&log));
+    assert_eq!(diagnosis.class, Some(FailureClass::MarkedFailed));
+}
+
+#[test]
+fn triage_tunnel_unreachable() {
+    let (mut job, mut log) = fixture_job();
+    job.assigned_tunnel_id = Some("20073ff17a234bec951b7a51a1bce2ad".to_string());
+    log.commands[3].result["value"]["message"] =
+        serde_json::json!("unknown error: net::ERR_TUNNEL_CONNECTION_FAILED");
+    log.commands[3].result["value"]["error"] = serde_json::json!("unknown error");
+    let diagnosis = Diagnosis::from_job(&job, Some(&log));
+    assert_eq!(diagnosis.class, Some(FailureClass::TunnelUnreachable));
+}
+
+#[test]
+fn triage_marked_failed() {
+    let (job, mut log) = fixture_job();
+    log.commands.remove(3);
+    let diagnosis = Diagnosis::from_job(&job, Some(&log));
+    assert_eq!(diagnosis.class, Some(FailureClass::MarkedFailed));
+}
+
+#[test]
+fn triage_without_a_log() {
+    let (mut job, _) = fixture_job();
+    job.consolidated_status = "error".to_string();
+    job.error = Some("session not created: No matching capabilities found".to_string());
+    let diagnosis = Diagnosis::from_job(&job, None);
+    assert_eq!(diagnosis.class, Some(FailureClass::SessionCreation));
+    assert_eq!(
+        diagnosis.evidence[0],
+        "No log.json, the session may never have started"
+    );
+
+    job.error = Some(
+        "Infrastructure Error -- The Sauce VMs failed to start the browser or device.".to_string(),
+    );
+    let diagnosis = Diagnosis::from_job(&job, None);
+    assert_eq!(diagnosis.class, Some(FailureClass::Infrastructure));
+    let plain = diagnosis.render_to_string(&super::render::Plain);
+    assert!(plain.contains("\nFailure: infrastructure error\n"));
+    assert!(plain.contains("\nNext step: Rerun the job, these are usually transient\n"));
+}
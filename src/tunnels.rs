@@ -1,4 +1,9 @@
+use super::api;
+use super::render::{self, Formatter, Render};
+use super::sauce_errors::{Result, ShelperError};
+use super::users;
 use serde::{Deserialize, Serialize};
+use std::io;
 
 /// The literal `meatadata`, a nested json object
 #[derive(Serialize, Deserialize, Debug)]
@@ -36,41 +41,58 @@ pub struct TunnelMetadata {
     #[serde(skip)]
     pub duration: Option<i64>,
     pub metadata: Metadata,
+    #[serde(skip)]
+    pub region: users::Region,
 }
 
 impl TunnelMetadata {
+    /// Fetch the metadata for `tunnel_id`, a tunnel started by `owner`.
+    /// `region` is the datacenter the tunnel was found in.
+    pub fn new(client: &api::SauceClient, owner: &str, tunnel_id: &str) -> Result<TunnelMetadata> {
+        let (resp, region) = client.find_tunnel(owner, tunnel_id)?;
+        let mut tunnel: TunnelMetadata =
+            serde_json::from_str(&resp).map_err(|e| ShelperError::deserialize(e, &resp))?;
+        tunnel.region = region;
+        Ok(tunnel)
+    }
+
     pub fn pretty_print(&self) {
-        println!("Id: {}", &self.id);
+        let _ = self.render(&render::Plain, &mut io::stdout());
+        // Leave for styling
+        println!()
+    }
+}
+
+impl Render for TunnelMetadata {
+    fn render(&self, f: &dyn Formatter, out: &mut dyn io::Write) -> io::Result<()> {
+        f.start(out, self.name.as_deref().unwrap_or(&self.id))?;
+        f.field(out, "Id", &self.id)?;
 
-        match &self.name {
-            None => (),
-            Some(name) => println!("Name(identifier): {}", name),
+        if let Some(name) = &self.name {
+            f.field(out, "Name(identifier)", name)?;
         }
 
-        println!("Release Version: {}", &self.metadata.release);
-        println!("Host: {}", &self.metadata.hostname);
-        println!("Owner: {}", &self.owner);
-        println!("Shared: {}", &self.shared);
-        println!("Maki: {}", &self.maki);
-        println!("Status: {}", &self.status);
+        f.field(out, "Release Version", &self.metadata.release)?;
+        f.field(out, "Host", &self.metadata.hostname)?;
+        f.field(out, "Owner", &self.owner)?;
+        f.field(out, "Shared", &self.shared.to_string())?;
+        f.field(out, "Maki", &self.maki)?;
+        f.status(out, "Status", &self.status)?;
+        f.field(out, "Region", &self.region.to_string())?;
 
-        match &self.metadata.open_file_limit {
-            None => (),
-            Some(limit) => println!("Open File Limit: {}", limit),
-        };
+        if let Some(limit) = &self.metadata.open_file_limit {
+            f.field(out, "Open File Limit", &limit.to_string())?;
+        }
 
-        match &self.no_ssl_bump_list {
-            None => (),
-            Some(no_bump_domains) => println!("No Bump Domains: {:?}", no_bump_domains),
+        if let Some(no_bump_domains) = &self.no_ssl_bump_list {
+            f.field(out, "No Bump Domains", &format!("{:?}", no_bump_domains))?;
         }
 
-        match &self.direct_domains_list {
-            None => (),
-            Some(direct_domains) => println!("Direct Domains: {:?}", direct_domains),
+        if let Some(direct_domains) = &self.direct_domains_list {
+            f.field(out, "Direct Domains", &format!("{:?}", direct_domains))?;
         }
-        println!("Command Line Flags: {}", &self.metadata.command);
-        // Leave for styling
-        println!("")
+        f.field(out, "Command Line Flags", &self.metadata.command)?;
+        f.end(out)
     }
 }
 
@@ -1,4 +1,6 @@
 use super::auth;
+use super::sauce_errors::{Result, ShelperError};
+use std::fmt;
 use std::str::FromStr;
 
 /// Represents a `User` at saucelabs.com. A user object is made up
@@ -11,11 +13,20 @@ pub struct User {
 }
 
 /// Which saucelabs.com datacenter that the user belongs to.
-/// Defaults to US.
-#[derive(Debug, PartialEq)]
+/// Defaults to US (us-west-1).
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
 pub enum Region {
+    /// eu-central-1
     EU,
+    /// us-west-1
+    #[default]
     US,
+    /// us-east-1, the headless datacenter
+    USEast1,
+    /// us-east-4
+    USEast4,
+    /// apac-southeast-1
+    APAC,
 }
 
 impl User {
@@ -23,35 +34,98 @@ impl User {
         username: Option<String>,
         access_key: Option<String>,
         region: Option<Region>,
-    ) -> User {
-        let creds = auth::set_credentials(username, access_key);
-        match region {
-            Some(region) => User {
-                creds: creds,
-                region: region,
-            },
-            None => User {
-                creds: creds,
-                region: Region::default(),
-            },
+    ) -> Result<User> {
+        let creds = auth::set_credentials(username, access_key)?;
+        Ok(User {
+            creds,
+            region: region.unwrap_or_default(),
+        })
+    }
+}
+
+impl Region {
+    /// Every datacenter, starting with the default
+    pub fn all() -> [Region; 5] {
+        [
+            Region::US,
+            Region::EU,
+            Region::USEast1,
+            Region::USEast4,
+            Region::APAC,
+        ]
+    }
+
+    /// Sauce's name for the datacenter, ex. `us-west-1`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Region::US => "us-west-1",
+            Region::EU => "eu-central-1",
+            Region::USEast1 => "us-east-1",
+            Region::USEast4 => "us-east-4",
+            Region::APAC => "apac-southeast-1",
+        }
+    }
+
+    /// Host serving the REST API for this datacenter
+    pub fn api_host(&self) -> String {
+        format!("https://api.{}.saucelabs.com", self.name())
+    }
+
+    /// Host serving the web app (test pages, builds) for this datacenter
+    pub fn app_host(&self) -> String {
+        match self {
+            Region::US => "https://app.saucelabs.com".to_string(),
+            other => format!("https://app.{}.saucelabs.com", other.name()),
         }
     }
 }
 
-impl Default for Region {
-    fn default() -> Self {
-        Region::US
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
     }
 }
 
 impl FromStr for Region {
-    type Err = &'static str;
+    type Err = ShelperError;
 
-    fn from_str(r: &str) -> Result<Self, Self::Err> {
-        match r {
-            "US" | "us" => Ok(Region::US),
-            "EU" | "eu" => Ok(Region::EU),
-            _ => Err("Region does not exist"),
+    fn from_str(r: &str) -> Result<Self> {
+        match r.to_lowercase().as_str() {
+            "us" | "us-west-1" => Ok(Region::US),
+            "eu" | "eu-central-1" => Ok(Region::EU),
+            "us-east-1" | "headless" => Ok(Region::USEast1),
+            "us-east-4" => Ok(Region::USEast4),
+            "apac" | "apac-southeast-1" => Ok(Region::APAC),
+            _ => Err(ShelperError::InvalidInput(format!(
+                "Region {} does not exist",
+                r
+            ))),
         }
     }
 }
+
+#[test]
+fn regions_parse_from_short_and_long_names() {
+    assert_eq!("US".parse::<Region>().unwrap(), Region::US);
+    assert_eq!("eu-central-1".parse::<Region>().unwrap(), Region::EU);
+    assert_eq!("headless".parse::<Region>().unwrap(), Region::USEast1);
+    assert_eq!("APAC".parse::<Region>().unwrap(), Region::APAC);
+    assert!("mars-north-1".parse::<Region>().is_err());
+    for region in Region::all().iter() {
+        assert_eq!(&region.name().parse::<Region>().unwrap(), region);
+    }
+}
+
+#[test]
+fn regions_know_their_hosts() {
+    assert_eq!(Region::US.api_host(), "https://api.us-west-1.saucelabs.com");
+    assert_eq!(Region::US.app_host(), "https://app.saucelabs.com");
+    assert_eq!(
+        Region::APAC.api_host(),
+        "https://api.apac-southeast-1.saucelabs.com"
+    );
+    assert_eq!(
+        Region::USEast4.app_host(),
+        "https://app.us-east-4.saucelabs.com"
+    );
+}